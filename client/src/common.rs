@@ -2,11 +2,15 @@ use crate::{
     utils::{self, MapScalars as _},
     web,
 };
+use anyhow::anyhow;
 use derive_more::{Add, Neg, Sub};
 use enum_map::Enum;
-use geo::{prelude::*, LineString, Rect};
+use geo::{prelude::*, CoordNum, Coordinate, Line, LineString, Polygon, Rect};
+use itertools::Itertools as _;
+use rustc_hash::FxHashMap;
 use serde::{de::Error as _, Deserialize, Deserializer, Serialize};
-use std::mem;
+use sha2::{Digest as _, Sha256};
+use std::{fmt, iter, mem, str::FromStr};
 use uuid::Uuid;
 
 /// A marker that indicates that the wrapped coordinates are the actual screen's ones.
@@ -19,7 +23,67 @@ impl<T> OnScreen<T> {
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+/// A zoom factor of the board, i.e. the length on the screen per unit length on the board.
+#[derive(Clone, Copy, PartialEq, PartialOrd, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Scale(f64);
+
+impl Default for Scale {
+    fn default() -> Self {
+        Self(1.)
+    }
+}
+
+impl Scale {
+    const MIN: f64 = 0.1;
+    const MAX: f64 = 8.;
+
+    /// Creates a new [`Scale`], clamping the given factor into the supported range.
+    pub fn new(factor: f64) -> Self {
+        Self(factor.clamp(Self::MIN, Self::MAX))
+    }
+
+    pub fn get(self) -> f64 {
+        self.0
+    }
+}
+
+/// A width of strokes, in pixels.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Width(u32);
+
+impl Default for Width {
+    fn default() -> Self {
+        Self(2)
+    }
+}
+
+impl Width {
+    pub const MIN: Self = Self(1);
+    pub const MAX: Self = Self(24);
+
+    /// Creates a new [`Width`], clamping the given value into the supported range.
+    pub fn new(width: u32) -> Self {
+        Self(width.clamp(Self::MIN.0, Self::MAX.0))
+    }
+
+    pub fn get(self) -> u32 {
+        self.0
+    }
+
+    /// Returns the distance from the center line to the edge of a stroke.
+    pub fn half(self) -> f64 {
+        f64::from(self.0) / 2.
+    }
+
+    /// Returns [`half`](Self::half) rounded up to an integer.
+    pub fn half_ceil(self) -> i32 {
+        self.half().ceil() as _
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
 #[serde(transparent)]
 pub struct PathId(Uuid);
 
@@ -30,12 +94,384 @@ impl PathId {
     }
 }
 
+/// Formats as a hyphenated UUID.
+impl fmt::Display for PathId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+/// An ID of a group of paths which behave as one.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct GroupId(Uuid);
+
+impl GroupId {
+    /// Generates a unique [`GroupId`].
+    pub fn gen() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
 #[derive(Clone, Debug, Serialize, Deserialize)]
 pub struct Path {
     pub color: Color,
+    pub width: Width,
+    pub shape: Shape,
     pub coords: LineString<i32>,
 }
 
+impl Path {
+    const ELLIPSE_SEGMENTS: usize = 64;
+    const ARROW_HEAD_ANGLE: f64 = std::f64::consts::PI / 6.;
+
+    /// Returns whether the path should be rendered beneath the other paths.
+    pub fn is_background(&self) -> bool {
+        matches!(self.shape, Shape::Image(_))
+    }
+
+    /// Returns the polyline along which the path is drawn, or [`None`] if it is `coords` itself.
+    pub fn outline(&self) -> Option<LineString<i32>> {
+        match self.shape {
+            Shape::Freehand | Shape::Line | Shape::Rectangle | Shape::Text(_) | Shape::Image(_) => {
+                None
+            }
+            Shape::Arrow => {
+                let end = utils::coord_map_scalars(*self.coords.0.last()?, f64::from);
+                let prev = utils::coord_map_scalars(*self.coords.0.iter().nth_back(1)?, f64::from);
+                let dir = prev - end;
+                let len = dir.x.hypot(dir.y);
+                if len == 0. {
+                    return None;
+                }
+                let head_len = 8. + 3. * f64::from(self.width.get());
+                let dir = dir / len * head_len;
+                let wing = |angle: f64| {
+                    let (sin, cos) = angle.sin_cos();
+                    let wing = end
+                        + Coordinate {
+                            x: dir.x * cos - dir.y * sin,
+                            y: dir.x * sin + dir.y * cos,
+                        };
+                    utils::coord_map_scalars(wing, |s| s.round() as i32)
+                };
+                let end = *self.coords.0.last()?;
+                let mut coords = self.coords.clone();
+                coords.0.extend([
+                    wing(Self::ARROW_HEAD_ANGLE),
+                    end,
+                    wing(-Self::ARROW_HEAD_ANGLE),
+                ]);
+                Some(coords)
+            }
+            Shape::Ellipse => {
+                let (center, u, v) = self.ellipse_axes()?;
+                let coords = (0..=Self::ELLIPSE_SEGMENTS)
+                    .map(|i| {
+                        let t = std::f64::consts::TAU * i as f64 / Self::ELLIPSE_SEGMENTS as f64;
+                        let (sin, cos) = t.sin_cos();
+                        utils::coord_map_scalars(center + u * cos + v * sin, |s| s.round() as i32)
+                    })
+                    .collect();
+                Some(coords)
+            }
+        }
+    }
+
+    /// Returns the segments along which the path is drawn, which every renderer follows.
+    pub fn segments(&self) -> Vec<Segment> {
+        match &self.shape {
+            Shape::Freehand => Segment::smooth(&self.coords).collect(),
+            Shape::Ellipse => match self.ellipse_axes() {
+                Some((center, u, v)) => Segment::ellipse(center, u, v).collect(),
+                None => Segment::polyline(&self.coords).collect(),
+            },
+            Shape::Line | Shape::Arrow | Shape::Rectangle | Shape::Text(_) | Shape::Image(_) => {
+                let outline = self.outline();
+                Segment::polyline(outline.as_ref().unwrap_or(&self.coords)).collect()
+            }
+        }
+    }
+
+    /// Cuts the path where the eraser of `radius` swept along `eraser` passes,
+    /// and returns the remaining pieces, or [`None`] if the eraser does not touch the path.
+    ///
+    /// Filled paths are never cut. The pieces of a shape are straight line segments.
+    pub fn cut(&self, eraser: Line<f64>, radius: f64) -> Option<Vec<Self>> {
+        let shape = match self.shape {
+            Shape::Freehand => Shape::Freehand,
+            Shape::Line | Shape::Arrow | Shape::Rectangle | Shape::Ellipse => Shape::Line,
+            Shape::Text(_) | Shape::Image(_) => return None,
+        };
+        let outline = self.outline();
+        let coords = outline.as_ref().unwrap_or(&self.coords);
+        let pieces = utils::cut_line_string(coords, eraser, radius + self.width.half())?;
+        let pieces = (pieces.into_iter())
+            .map(|coords| Self {
+                color: self.color,
+                width: self.width,
+                shape: shape.clone(),
+                coords,
+            })
+            .collect();
+        Some(pieces)
+    }
+
+    /// Returns the first corner and the two edges from it of the parallelogram
+    /// whose corners are the first four coordinates.
+    pub fn parallelogram(&self) -> Option<(Coordinate<f64>, Coordinate<f64>, Coordinate<f64>)> {
+        let corner = |i| Some(utils::coord_map_scalars(*self.coords.0.get(i)?, f64::from));
+        let (p_0, p_1, p_3) = (corner(0)?, corner(1)?, corner(3)?);
+        Some((p_0, p_1 - p_0, p_3 - p_0))
+    }
+
+    /// Returns the center and the two conjugate semi-axes of an ellipse,
+    /// which is inscribed in the parallelogram whose corners are the first four coordinates.
+    pub fn ellipse_axes(&self) -> Option<(Coordinate<f64>, Coordinate<f64>, Coordinate<f64>)> {
+        let (origin, u, v) = self.parallelogram()?;
+        Some((origin + (u + v) / 2., u / 2., v / 2.))
+    }
+}
+
+/// A segment of the outline of a path, which continues from the end of the previous one.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub enum Segment {
+    MoveTo(Coordinate<f64>),
+    LineTo(Coordinate<f64>),
+    /// A quadratic Bézier curve with the control point and the end.
+    QuadTo(Coordinate<f64>, Coordinate<f64>),
+    /// A cubic Bézier curve with the two control points and the end.
+    CubicTo(Coordinate<f64>, Coordinate<f64>, Coordinate<f64>),
+    /// A straight line back to the start of the subpath.
+    Close,
+}
+
+impl Segment {
+    /// Returns the segments of the polyline smoothed by quadratic curves,
+    /// which pass through the midpoints of its edges.
+    pub fn smooth<T>(coords: &LineString<T>) -> impl Iterator<Item = Self> + '_
+    where
+        T: CoordNum,
+        f64: From<T>,
+    {
+        use itertools::Position;
+        let f = |c| utils::coord_map_scalars(c, f64::from);
+        (coords.0.iter().copied().tuple_windows().with_position()).flat_map(move |pair| {
+            let (first, second) = match pair {
+                Position::Only((c_0, c_1)) => (Self::MoveTo(f(c_0)), Some(Self::LineTo(f(c_1)))),
+                Position::First((c_0, c_1)) => {
+                    let mid = (f(c_0) + f(c_1)) / 2.;
+                    (Self::MoveTo(f(c_0)), Some(Self::LineTo(mid)))
+                }
+                Position::Middle((c_0, c_1)) => {
+                    let mid = (f(c_0) + f(c_1)) / 2.;
+                    (Self::QuadTo(f(c_0), mid), None)
+                }
+                Position::Last((_, c_1)) => (Self::LineTo(f(c_1)), None),
+            };
+            iter::once(first).chain(second)
+        })
+    }
+
+    /// Returns the segments of the polyline as it is.
+    pub fn polyline<T>(coords: &LineString<T>) -> impl Iterator<Item = Self> + '_
+    where
+        T: CoordNum,
+        f64: From<T>,
+    {
+        (coords.0.iter().enumerate()).map(|(i, &c)| {
+            let c = utils::coord_map_scalars(c, f64::from);
+            if i == 0 {
+                Self::MoveTo(c)
+            } else {
+                Self::LineTo(c)
+            }
+        })
+    }
+
+    /// Returns the segments of the ellipse `center + u cos θ + v sin θ` (0 ≤ θ < 2π)
+    /// approximated by four cubic Bézier curves.
+    pub fn ellipse(
+        center: Coordinate<f64>,
+        u: Coordinate<f64>,
+        v: Coordinate<f64>,
+    ) -> impl Iterator<Item = Self> {
+        const K: f64 = 0.5522847498; // 4/3 (√2 - 1)
+        let curves = [(u, v), (v, -u), (-u, -v), (-v, u)]
+            .into_iter()
+            .map(move |(a, b)| Self::CubicTo(center + a + b * K, center + a * K + b, center + b));
+        iter::once(Self::MoveTo(center + u))
+            .chain(curves)
+            .chain(iter::once(Self::Close))
+    }
+}
+
+/// A shape of a path, which determines how its coordinates are connected.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum Shape {
+    /// A freehand stroke, smoothed by quadratic curves.
+    Freehand,
+    /// Straight line segments.
+    Line,
+    /// Straight line segments with an arrowhead at the last coordinate.
+    Arrow,
+    /// A closed polyline of four corners.
+    Rectangle,
+    /// An ellipse inscribed in the closed polyline of four corners.
+    Ellipse,
+    /// A text laid out in the closed polyline of four corners.
+    Text(Text),
+    /// A raster image laid out in the closed polyline of four corners.
+    Image(ImageHash),
+}
+
+impl Shape {
+    /// Returns the coordinates of the shape spanned by dragging from `start` to `end`.
+    pub fn coords_between(&self, start: Coordinate<i32>, end: Coordinate<i32>) -> LineString<i32> {
+        match self {
+            Self::Freehand | Self::Line | Self::Arrow => vec![start, end].into(),
+            Self::Rectangle | Self::Ellipse | Self::Text(_) | Self::Image(_) => vec![
+                start,
+                Coordinate {
+                    x: end.x,
+                    y: start.y,
+                },
+                end,
+                Coordinate {
+                    x: start.x,
+                    y: end.y,
+                },
+                start,
+            ]
+            .into(),
+        }
+    }
+}
+
+/// A text box, whose lines are separated by line feeds.
+#[derive(Clone, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct Text {
+    pub content: String,
+    pub font_size: FontSize,
+}
+
+impl Text {
+    /// The height of a line relative to the font size.
+    pub const LINE_HEIGHT: f64 = 1.25;
+
+    /// Returns the CSS font shorthand for the text.
+    pub fn font(&self) -> String {
+        format!("{}px sans-serif", self.font_size.get())
+    }
+
+    pub fn lines(&self) -> impl Iterator<Item = &str> {
+        self.content.lines()
+    }
+
+    pub fn line_height(&self) -> f64 {
+        f64::from(self.font_size.get()) * Self::LINE_HEIGHT
+    }
+}
+
+/// A radius of the partial eraser, in pixels.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct EraserRadius(u32);
+
+impl Default for EraserRadius {
+    fn default() -> Self {
+        Self(8)
+    }
+}
+
+impl EraserRadius {
+    pub const MIN: Self = Self(1);
+    pub const MAX: Self = Self(64);
+
+    /// Creates a new [`EraserRadius`], clamping the given value into the supported range.
+    pub fn new(radius: u32) -> Self {
+        Self(radius.clamp(Self::MIN.0, Self::MAX.0))
+    }
+
+    pub fn get(self) -> u32 {
+        self.0
+    }
+}
+
+/// A font size of texts, in pixels.
+#[derive(Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Hash, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct FontSize(u32);
+
+impl Default for FontSize {
+    fn default() -> Self {
+        Self(24)
+    }
+}
+
+impl FontSize {
+    pub const MIN: Self = Self(8);
+    pub const MAX: Self = Self(96);
+
+    /// Creates a new [`FontSize`], clamping the given value into the supported range.
+    pub fn new(size: u32) -> Self {
+        Self(size.clamp(Self::MIN.0, Self::MAX.0))
+    }
+
+    pub fn get(self) -> u32 {
+        self.0
+    }
+}
+
+/// The SHA-256 hash of the content of an image, by which the image data is stored.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct ImageHash([u8; 32]);
+
+/// Formats as a lowercase hexadecimal string.
+impl fmt::Display for ImageHash {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.iter().try_for_each(|b| write!(f, "{:02x}", b))
+    }
+}
+
+/// An encoded raster image.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct ImageData {
+    /// The MIME type, which is `image/png` or `image/jpeg`.
+    pub mime_type: String,
+    pub bytes: Vec<u8>,
+}
+
+impl ImageData {
+    pub const MIME_TYPES: [&'static str; 2] = ["image/png", "image/jpeg"];
+
+    pub fn hash(&self) -> ImageHash {
+        ImageHash(Sha256::digest(&self.bytes).into())
+    }
+
+    /// Returns the `data:` URL of the image.
+    pub fn url(&self) -> String {
+        format!(
+            "data:{};base64,{}",
+            self.mime_type,
+            base64::encode(&self.bytes)
+        )
+    }
+}
+
+/// Paths copied to the clipboard, with the images they reference.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct Clip {
+    pub paths: Vec<RenderablePath>,
+    pub images: FxHashMap<ImageHash, ImageData>,
+}
+
+impl Clip {
+    /// The MIME type of the clip on the clipboard.
+    pub const MIME_TYPE: &'static str = "application/x-papirs";
+}
+
 #[derive(Clone, Debug, Serialize)]
 #[serde(transparent)]
 pub struct Renderable<T> {
@@ -59,6 +495,8 @@ impl<T> Renderable<T> {
 pub struct RenderablePath {
     path: Renderable<Path>,
     #[serde(skip)]
+    outline: Option<LineString<i32>>,
+    #[serde(skip)]
     bounding_rect: Renderable<Rect<i32>>,
 }
 
@@ -75,15 +513,20 @@ impl<'de> Deserialize<'de> for RenderablePath {
 impl RenderablePath {
     /// Creates a new [`RenderablePath`]. Returns [`None`] when the given path is empty.
     pub fn new(path: Path) -> Option<Self> {
-        let bounding_rect = path.coords.bounding_rect()?;
-        let bounding_rect_ex1 = utils::expand_rect(bounding_rect.map_scalars(f64::from), 4.5);
-        let bounding_rect_ex2 = utils::expand_rect(bounding_rect, 5);
+        let outline = path.outline();
+        let bounding_rect = outline.as_ref().unwrap_or(&path.coords).bounding_rect()?;
+        let margin = path.width.half_ceil() + 4;
+        let bounding_rect_ex1 = utils::expand_rect(
+            bounding_rect.map_scalars(f64::from),
+            f64::from(margin) - 0.5,
+        );
+        let bounding_rect_ex2 = utils::expand_rect(bounding_rect, margin);
+
+        let obj = web::Path::from_segments(path.segments());
 
         Some(Self {
-            path: Renderable {
-                obj: (&path.coords).into(),
-                inner: path,
-            },
+            path: Renderable { obj, inner: path },
+            outline,
             bounding_rect: Renderable {
                 obj: bounding_rect_ex1.into(),
                 inner: bounding_rect_ex2,
@@ -93,6 +536,7 @@ impl RenderablePath {
 
     pub fn take(&mut self) -> Path {
         Path {
+            shape: mem::replace(&mut self.path.inner.shape, Shape::Freehand),
             coords: LineString(mem::take(&mut self.path.inner.coords.0)),
             ..self.path.inner
         }
@@ -102,38 +546,305 @@ impl RenderablePath {
         &self.path
     }
 
+    /// Returns the polyline along which the path is drawn.
+    pub fn outline(&self) -> &LineString<i32> {
+        self.outline.as_ref().unwrap_or(&self.path.inner.coords)
+    }
+
+    /// Returns whether the path should be rendered beneath the other paths.
+    pub fn is_background(&self) -> bool {
+        self.path.inner.is_background()
+    }
+
+    /// Returns the region to be hit as well as the outline, if the path is filled.
+    pub fn area(&self) -> Option<Polygon<i32>> {
+        match self.path.inner.shape {
+            Shape::Text(_) | Shape::Image(_) => Some(Polygon::new(self.outline().clone(), vec![])),
+            _ => None,
+        }
+    }
+
     pub fn bounding_rect(&self) -> &Renderable<Rect<i32>> {
         &self.bounding_rect
     }
 }
 
-#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Enum, Serialize, Deserialize)]
-pub enum Color {
-    Black,
-    Red,
-    Orange,
-    Green,
-    Blue,
-    SkyBlue,
+/// An RGBA color.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub struct Color {
+    r: u8,
+    g: u8,
+    b: u8,
+    a: u8,
 }
 
 impl Default for Color {
     fn default() -> Self {
-        Self::Black
+        Self::BLACK
     }
 }
 
 impl Color {
+    pub const BLACK: Self = Self::opaque(0, 0, 0);
+    pub const WHITE: Self = Self::opaque(255, 255, 255);
+    pub const RED: Self = Self::opaque(255, 75, 0);
+    pub const ORANGE: Self = Self::opaque(246, 170, 0);
+    pub const GREEN: Self = Self::opaque(3, 175, 122);
+    pub const BLUE: Self = Self::opaque(0, 90, 255);
+    pub const SKY_BLUE: Self = Self::opaque(77, 196, 255);
+
+    pub const fn new(r: u8, g: u8, b: u8, a: u8) -> Self {
+        Self { r, g, b, a }
+    }
+
+    pub const fn opaque(r: u8, g: u8, b: u8) -> Self {
+        Self::new(r, g, b, u8::MAX)
+    }
+
     pub fn rgb(self) -> (u8, u8, u8) {
-        match self {
-            Self::Black => (0, 0, 0),
-            Self::Red => (255, 75, 0),
-            Self::Orange => (246, 170, 0),
-            Self::Green => (3, 175, 122),
-            Self::Blue => (0, 90, 255),
-            Self::SkyBlue => (77, 196, 255),
+        (self.r, self.g, self.b)
+    }
+
+    pub fn alpha(self) -> u8 {
+        self.a
+    }
+
+    pub fn with_alpha(self, a: u8) -> Self {
+        Self { a, ..self }
+    }
+}
+
+/// Formats as `#rrggbbaa`.
+impl fmt::Display for Color {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "#{:02x}{:02x}{:02x}{:02x}",
+            self.r, self.g, self.b, self.a
+        )
+    }
+}
+
+/// Parses `#rrggbb` or `#rrggbbaa`.
+impl FromStr for Color {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        let hex = (s.strip_prefix('#'))
+            .filter(|hex| hex.is_ascii() && matches!(hex.len(), 6 | 8))
+            .ok_or_else(|| anyhow!("invalid color: {}", s))?;
+        let channel = |i: usize| u8::from_str_radix(hex.get(i..i + 2).unwrap_or("ff"), 16);
+        Ok(Self::new(
+            channel(0)?,
+            channel(2)?,
+            channel(4)?,
+            channel(6)?,
+        ))
+    }
+}
+
+/// A list of colors that the user can pick quickly.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct Palette(pub Vec<Color>);
+
+impl Palette {
+    /// Returns the color in the palette closest to `color` in RGB, or [`None`] if it is empty.
+    pub fn nearest(&self, color: Color) -> Option<Color> {
+        let (r, g, b) = color.rgb();
+        self.0.iter().copied().min_by_key(|c| {
+            let (r_1, g_1, b_1) = c.rgb();
+            let d = |x: u8, y: u8| (i32::from(x) - i32::from(y)).pow(2);
+            d(r, r_1) + d(g, g_1) + d(b, b_1)
+        })
+    }
+}
+
+impl Default for Palette {
+    fn default() -> Self {
+        Self(vec![
+            Color::BLACK,
+            Color::RED,
+            Color::ORANGE,
+            Color::GREEN,
+            Color::BLUE,
+            Color::SKY_BLUE,
+        ])
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct DocLayerId(Uuid);
+
+impl DocLayerId {
+    /// Generates a unique [`DocLayerId`].
+    pub fn gen() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+/// Formats as a hyphenated UUID.
+impl fmt::Display for DocLayerId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for DocLayerId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Ok(Self(s.parse()?))
+    }
+}
+
+/// A layer of the document managed by the user, which each path belongs to.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct DocLayer {
+    pub id: DocLayerId,
+    pub name: String,
+    pub visible: bool,
+    /// Whether the paths in the layer cannot be selected nor erased.
+    pub locked: bool,
+}
+
+impl DocLayer {
+    pub fn new(name: String) -> Self {
+        Self {
+            id: DocLayerId::gen(),
+            name,
+            visible: true,
+            locked: false,
         }
     }
+
+    /// Returns whether the paths in the layer can be selected or erased.
+    pub fn is_editable(&self) -> bool {
+        self.visible && !self.locked
+    }
+}
+
+/// The layers of the document, which are never empty.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct DocLayers {
+    /// The layers from the bottom.
+    pub list: Vec<DocLayer>,
+    /// The layer which new paths go into.
+    pub active: DocLayerId,
+}
+
+impl Default for DocLayers {
+    fn default() -> Self {
+        let layer = DocLayer::new(Self::default_name(1));
+        Self {
+            active: layer.id,
+            list: vec![layer],
+        }
+    }
+}
+
+impl DocLayers {
+    pub fn default_name(n: usize) -> String {
+        format!("Layer {}", n)
+    }
+
+    /// Returns the index of the layer from the bottom.
+    pub fn position(&self, id: DocLayerId) -> Option<usize> {
+        self.list.iter().position(|layer| layer.id == id)
+    }
+
+    pub fn get(&self, id: DocLayerId) -> Option<&DocLayer> {
+        self.list.iter().find(|layer| layer.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: DocLayerId) -> Option<&mut DocLayer> {
+        self.list.iter_mut().find(|layer| layer.id == id)
+    }
+
+    /// Returns the layer that a path recorded as belonging to `id` belongs to,
+    /// which is the bottom one if there is no such layer.
+    pub fn resolve(&self, id: Option<DocLayerId>) -> &DocLayer {
+        id.and_then(|id| self.get(id)).unwrap_or(&self.list[0])
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct BoardId(Uuid);
+
+impl BoardId {
+    /// Generates a unique [`BoardId`].
+    pub fn gen() -> Self {
+        Self(Uuid::new_v4())
+    }
+}
+
+/// Formats as a hyphenated UUID.
+impl fmt::Display for BoardId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        self.0.fmt(f)
+    }
+}
+
+impl FromStr for BoardId {
+    type Err = anyhow::Error;
+
+    fn from_str(s: &str) -> anyhow::Result<Self> {
+        Ok(Self(s.parse()?))
+    }
+}
+
+/// A board stored in the browser, whose fields are stored under its own keys.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Board {
+    pub id: BoardId,
+    pub name: String,
+}
+
+impl Board {
+    pub fn new(name: String) -> Self {
+        Self {
+            id: BoardId::gen(),
+            name,
+        }
+    }
+}
+
+/// The boards stored in the browser, which are never empty.
+#[derive(Clone, PartialEq, Eq, Debug, Serialize, Deserialize)]
+pub struct Boards {
+    pub list: Vec<Board>,
+    /// The board which is loaded.
+    pub active: BoardId,
+}
+
+impl Default for Boards {
+    fn default() -> Self {
+        let board = Board::new(Self::default_name(1));
+        Self {
+            active: board.id,
+            list: vec![board],
+        }
+    }
+}
+
+impl Boards {
+    pub fn default_name(n: usize) -> String {
+        format!("Board {}", n)
+    }
+
+    pub fn position(&self, id: BoardId) -> Option<usize> {
+        self.list.iter().position(|board| board.id == id)
+    }
+
+    pub fn get(&self, id: BoardId) -> Option<&Board> {
+        self.list.iter().find(|board| board.id == id)
+    }
+
+    pub fn get_mut(&mut self, id: BoardId) -> Option<&mut Board> {
+        self.list.iter_mut().find(|board| board.id == id)
+    }
 }
 
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Enum, Serialize, Deserialize)]
@@ -141,6 +852,36 @@ pub enum Tool {
     Selector,
     Pen,
     Eraser,
+    Line,
+    Arrow,
+    Rectangle,
+    Ellipse,
+    Text,
+    PartialEraser,
+    Lasso,
+}
+
+impl Tool {
+    /// Returns whether the tool selects paths, keeping the selection when switched to.
+    pub fn is_selector(self) -> bool {
+        matches!(self, Self::Selector | Self::Lasso)
+    }
+
+    /// Returns the shape drawn by the tool, if it is a shape tool.
+    pub fn shape(self) -> Option<Shape> {
+        match self {
+            Self::Selector
+            | Self::Pen
+            | Self::Eraser
+            | Self::Text
+            | Self::PartialEraser
+            | Self::Lasso => None,
+            Self::Line => Some(Shape::Line),
+            Self::Arrow => Some(Shape::Arrow),
+            Self::Rectangle => Some(Shape::Rectangle),
+            Self::Ellipse => Some(Shape::Ellipse),
+        }
+    }
 }
 
 impl Default for Tool {
@@ -148,3 +889,18 @@ impl Default for Tool {
         Self::Pen
     }
 }
+
+/// Which paths the lasso selects.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize)]
+pub enum LassoMode {
+    /// Paths entirely inside the lasso.
+    Whole,
+    /// Paths at least partly inside the lasso.
+    Partial,
+}
+
+impl Default for LassoMode {
+    fn default() -> Self {
+        Self::Whole
+    }
+}
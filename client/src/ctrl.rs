@@ -1,13 +1,17 @@
 //! A controller, which recieves events from the view and manipulates the model.
 
 use crate::{
-    common::{Color, OnScreen, Path, PathId, RenderablePath, Tool},
-    model::{self, Model},
-    utils::{self, MapScalars},
+    common::{
+        BoardId, Boards, Clip, Color, DocLayerId, DocLayers, EraserRadius, FontSize, ImageData,
+        LassoMode, OnScreen, Path, PathId, RenderablePath, Shape, Tool, Width,
+    },
+    document::Document,
+    model::{self, Handle, Model},
+    utils::{self, Affine, MapScalars},
     web,
 };
 use enum_dispatch::enum_dispatch;
-use geo::{prelude::*, Coordinate, Line, LineString, Rect};
+use geo::{prelude::*, Coordinate, Line, LineString, Polygon, Rect};
 use rustc_hash::FxHashSet;
 use std::iter;
 
@@ -22,9 +26,14 @@ trait Handler {
 enum AnyHandler {
     Scroll(ScrollHandler),
     Select(SelectHandler),
+    Lasso(LassoHandler),
     Shift(ShiftHandler),
+    Transform(TransformHandler),
     Draw(DrawHandler),
+    Shape(ShapeHandler),
+    Text(TextHandler),
     Erase(EraseHandler),
+    PartialErase(PartialEraseHandler),
 }
 
 #[derive(Debug)]
@@ -40,8 +49,7 @@ impl ScrollHandler {
 
 impl Handler for ScrollHandler {
     fn move_to(&mut self, mut model: model::DeferCommit, coord: OnScreen<Coordinate<i32>>) {
-        let delta = model.delta_of(coord - self.prev_coord);
-        model.scroll(delta);
+        model.scroll(coord - self.prev_coord);
         self.prev_coord = coord;
     }
 
@@ -80,30 +88,75 @@ impl Handler for SelectHandler {
         self.prev_coord = coord;
     }
 
-    fn finish(self, model: model::DeferCommit) {
+    fn finish(self, mut model: model::DeferCommit) {
+        model.temp_layer().clear();
+        if self.prev_coord == self.start_coord {
+            model.select_path_at(self.start_coord);
+        }
+    }
+}
+
+#[derive(Debug)]
+struct LassoHandler {
+    coords: Vec<Coordinate<i32>>,
+}
+
+impl LassoHandler {
+    pub fn new(model: &Model, coord: OnScreen<Coordinate<i32>>) -> Self {
+        let coord = model.coord_at(coord);
+        Self {
+            coords: vec![coord],
+        }
+    }
+}
+
+impl Handler for LassoHandler {
+    fn move_to(&mut self, model: model::DeferCommit, coord: OnScreen<Coordinate<i32>>) {
+        let coord = model.coord_at(coord);
+        if self.coords.last() == Some(&coord) {
+            return;
+        }
+        self.coords.push(coord);
+        model.temp_layer().clear();
+        model.temp_layer().render_lasso(&self.coords);
+    }
+
+    fn finish(self, mut model: model::DeferCommit) {
         model.temp_layer().clear();
+        if self.coords.len() < 3 {
+            return;
+        }
+        let lasso = Polygon::new(self.coords.into(), vec![]);
+        let mode = model.lasso_mode();
+        model.select_paths_in(lasso, mode);
     }
 }
 
 #[derive(Debug)]
 struct ShiftHandler {
     shifting_path_ids: FxHashSet<PathId>,
+    /// Whether to leave the paths in place and shift copies of them instead.
+    copying: bool,
     start_coord: Coordinate<i32>,
     prev_coord: Coordinate<i32>,
 }
 
 impl ShiftHandler {
-    pub fn new(mut model: model::DeferCommit, coord: OnScreen<Coordinate<i32>>) -> Self {
+    pub fn new(
+        mut model: model::DeferCommit,
+        coord: OnScreen<Coordinate<i32>>,
+        copying: bool,
+    ) -> Self {
         let coord = model.coord_at(coord);
-        let shifting_path_ids = model
-            .selected_paths()
-            .map(|(id, _)| id)
-            .collect::<FxHashSet<_>>();
-        for &id in &shifting_path_ids {
-            model.hide_path(id);
+        let shifting_path_ids = model.with_groups(model.selected_paths().map(|(id, _)| id));
+        if !copying {
+            for &id in &shifting_path_ids {
+                model.hide_path(id);
+            }
         }
         let this = Self {
             shifting_path_ids,
+            copying,
             start_coord: coord,
             prev_coord: coord,
         };
@@ -134,6 +187,12 @@ impl Handler for ShiftHandler {
         let delta = self.prev_coord - self.start_coord;
         model.temp_layer().clear();
         model.temp_layer().translate(-delta);
+        if self.copying {
+            if delta != Coordinate::zero() {
+                model.duplicate_paths(self.shifting_path_ids, delta);
+            }
+            return;
+        }
         model.shift_paths(self.shifting_path_ids.iter().copied(), delta);
         for id in self.shifting_path_ids {
             model.unhide_path(id);
@@ -141,6 +200,129 @@ impl Handler for ShiftHandler {
     }
 }
 
+#[derive(Debug)]
+struct TransformHandler {
+    handle: Handle,
+    transforming_path_ids: FxHashSet<PathId>,
+    /// The bounding rectangle of the paths before transforming.
+    rect: Rect<i32>,
+    start_coord: Coordinate<f64>,
+    transform: Affine,
+    /// Whether to keep the aspect ratio in scaling, or to snap the angle in rotation.
+    constrained: bool,
+}
+
+impl TransformHandler {
+    /// The minimum absolute value of the scale factors, which keeps the transformation invertible.
+    const MIN_SCALE: f64 = 0.01;
+    /// The angle to which rotation snaps when constrained.
+    const SNAP_ANGLE: f64 = std::f64::consts::PI / 12.;
+
+    pub fn new(
+        mut model: model::DeferCommit,
+        handle: Handle,
+        coord: OnScreen<Coordinate<i32>>,
+    ) -> Self {
+        let rect = model.selection_rect().expect("no paths selected");
+        let coord = model.coord_at(coord);
+        let transforming_path_ids = model
+            .selected_paths()
+            .map(|(id, _)| id)
+            .collect::<FxHashSet<_>>();
+        for &id in &transforming_path_ids {
+            model.hide_path(id);
+        }
+        let this = Self {
+            handle,
+            transforming_path_ids,
+            rect,
+            start_coord: utils::coord_map_scalars(coord, f64::from),
+            transform: Affine::IDENTITY,
+            constrained: false,
+        };
+        this.rerender(&*model);
+        this
+    }
+
+    pub fn set_constrained(&mut self, constrained: bool) {
+        self.constrained = constrained;
+    }
+
+    fn transform_to(&self, coord: Coordinate<f64>) -> Affine {
+        let corners =
+            utils::rect_corners(self.rect).map(|c| utils::coord_map_scalars(c, f64::from));
+        match self.handle {
+            Handle::Scale(i) => {
+                let anchor = corners[(i + 2) % 4];
+                let factor = |to: f64, from: f64, anchor: f64| {
+                    let factor = if from == anchor {
+                        1.
+                    } else {
+                        (to - anchor) / (from - anchor)
+                    };
+                    if factor.abs() < Self::MIN_SCALE {
+                        Self::MIN_SCALE.copysign(factor)
+                    } else {
+                        factor
+                    }
+                };
+                let mut x = factor(coord.x, corners[i].x, anchor.x);
+                let mut y = factor(coord.y, corners[i].y, anchor.y);
+                if self.constrained {
+                    let s = x.abs().max(y.abs());
+                    x = s.copysign(x);
+                    y = s.copysign(y);
+                }
+                Affine::scaling(x, y).about(anchor)
+            }
+            Handle::Rotate => {
+                let center = (corners[0] + corners[2]) / 2.;
+                let angle_of = |c: Coordinate<f64>| (c.y - center.y).atan2(c.x - center.x);
+                let mut angle = angle_of(coord) - angle_of(self.start_coord);
+                if self.constrained {
+                    angle = (angle / Self::SNAP_ANGLE).round() * Self::SNAP_ANGLE;
+                }
+                Affine::rotation(angle).about(center)
+            }
+        }
+    }
+
+    fn rerender(&self, model: &Model) {
+        model.temp_layer().clear();
+        for &id in &self.transforming_path_ids {
+            let mut path = model.path(id).get().get().clone();
+            path.coords = self.transform.apply_to_line_string(&path.coords);
+            if let Some(path) = RenderablePath::new(path) {
+                model.temp_layer().render_path(&path);
+            }
+        }
+        let corners =
+            utils::rect_corners(self.rect).map(|c| utils::coord_map_scalars(c, f64::from));
+        let outline = (corners.iter().chain(iter::once(&corners[0])))
+            .map(|&c| utils::coord_map_scalars(self.transform.apply(c), |s| s.round() as i32))
+            .collect::<Vec<_>>();
+        model.temp_layer().render_lasso(&outline);
+    }
+}
+
+impl Handler for TransformHandler {
+    fn move_to(&mut self, model: model::DeferCommit, coord: OnScreen<Coordinate<i32>>) {
+        let coord = utils::coord_map_scalars(model.coord_at(coord), f64::from);
+        self.transform = self.transform_to(coord);
+        self.rerender(&*model);
+    }
+
+    fn finish(self, mut model: model::DeferCommit) {
+        model.temp_layer().clear();
+        for &id in &self.transforming_path_ids {
+            model.unhide_path(id);
+        }
+        if self.transform != Affine::IDENTITY {
+            model.transform_paths(self.transforming_path_ids, self.transform);
+        }
+    }
+}
+
 #[derive(Debug)]
 struct DrawHandler {
     coords: Vec<Coordinate<i32>>,
@@ -172,7 +354,7 @@ impl Handler for DrawHandler {
         };
         model
             .temp_layer()
-            .render_curve(model.pen_color(), start, control, end);
+            .render_curve(model.pen_color(), model.pen_width(), start, control, end);
         self.coords.push(coord);
     }
 
@@ -183,6 +365,8 @@ impl Handler for DrawHandler {
             .map_scalars(|s| s as _);
         let path = Path {
             color: model.pen_color(),
+            width: model.pen_width(),
+            shape: Shape::Freehand,
             coords,
         };
         let path = RenderablePath::new(path).expect("`path` should not be empty");
@@ -191,6 +375,76 @@ impl Handler for DrawHandler {
     }
 }
 
+#[derive(Debug)]
+struct ShapeHandler {
+    shape: Shape,
+    start_coord: Coordinate<i32>,
+    prev_coord: Coordinate<i32>,
+}
+
+impl ShapeHandler {
+    pub fn new(model: &Model, shape: Shape, coord: OnScreen<Coordinate<i32>>) -> Self {
+        let coord = model.coord_at(coord);
+        Self {
+            shape,
+            start_coord: coord,
+            prev_coord: coord,
+        }
+    }
+
+    /// Returns the shape spanned so far, or [`None`] if it is degenerate.
+    fn path(&self, model: &Model) -> Option<RenderablePath> {
+        if self.start_coord == self.prev_coord {
+            return None;
+        }
+        RenderablePath::new(Path {
+            color: model.pen_color(),
+            width: model.pen_width(),
+            shape: self.shape.clone(),
+            coords: (self.shape).coords_between(self.start_coord, self.prev_coord),
+        })
+    }
+}
+
+impl Handler for ShapeHandler {
+    fn move_to(&mut self, model: model::DeferCommit, coord: OnScreen<Coordinate<i32>>) {
+        self.prev_coord = model.coord_at(coord);
+        model.temp_layer().clear();
+        if let Some(path) = self.path(&*model) {
+            model.temp_layer().render_path(&path);
+        }
+    }
+
+    fn finish(self, mut model: model::DeferCommit) {
+        model.temp_layer().clear();
+        if let Some(path) = self.path(&*model) {
+            model.insert_paths(iter::once((PathId::gen(), path)));
+        }
+    }
+}
+
+#[derive(Debug)]
+struct TextHandler {
+    coord: Coordinate<i32>,
+}
+
+impl TextHandler {
+    pub fn new(model: &Model, coord: OnScreen<Coordinate<i32>>) -> Self {
+        Self {
+            coord: model.coord_at(coord),
+        }
+    }
+}
+
+impl Handler for TextHandler {
+    fn move_to(&mut self, _: model::DeferCommit, _: OnScreen<Coordinate<i32>>) {}
+
+    // Opens the editor on release, since pressing would move the focus away from it.
+    fn finish(self, mut model: model::DeferCommit) {
+        model.start_text_editing(self.coord);
+    }
+}
+
 #[derive(Debug)]
 struct EraseHandler {
     removing_path_ids: FxHashSet<PathId>,
@@ -211,11 +465,23 @@ impl Handler for EraseHandler {
     fn move_to(&mut self, mut model: model::DeferCommit, coord: OnScreen<Coordinate<i32>>) {
         let coord = model.coord_at(coord);
         let eraser_line = Line::new(self.prev_coord, coord);
+        let eraser_line_f = eraser_line.map_scalars(f64::from);
         let ids = model
-            .bounding_tile_items(eraser_line)
+            .bounding_tile_items(utils::expand_rect(
+                eraser_line.bounding_rect(),
+                Width::MAX.half_ceil(),
+            ))
             .filter(|(id, lines)| {
-                !self.removing_path_ids.contains(id)
-                    && lines.iter().any(|line| line.intersects(&eraser_line))
+                if self.removing_path_ids.contains(id) {
+                    return false;
+                }
+                let path = model.path(*id);
+                let half_width = path.get().get().width.half();
+                lines.iter().any(|line| {
+                    line.map_scalars(f64::from)
+                        .euclidean_distance(&eraser_line_f)
+                        <= half_width
+                }) || matches!(path.area(), Some(area) if area.intersects(&eraser_line))
             })
             .map(|(id, _)| id)
             .collect::<Vec<_>>();
@@ -231,16 +497,103 @@ impl Handler for EraseHandler {
     }
 }
 
+#[derive(Debug)]
+struct PartialEraseHandler {
+    /// The paths touched by the eraser, which are hidden until finishing.
+    cut_path_ids: FxHashSet<PathId>,
+    /// The pieces remaining from the paths touched with the IDs of them,
+    /// which are rendered on the temporary layer.
+    pieces: Vec<(PathId, RenderablePath)>,
+    prev_coord: Coordinate<i32>,
+}
+
+impl PartialEraseHandler {
+    pub fn new(model: &Model, coord: OnScreen<Coordinate<i32>>) -> Self {
+        let coord = model.coord_at(coord);
+        Self {
+            cut_path_ids: FxHashSet::default(),
+            pieces: vec![],
+            prev_coord: coord,
+        }
+    }
+}
+
+impl Handler for PartialEraseHandler {
+    fn move_to(&mut self, mut model: model::DeferCommit, coord: OnScreen<Coordinate<i32>>) {
+        let coord = model.coord_at(coord);
+        let radius = f64::from(model.eraser_radius().get());
+        let eraser_line = Line::new(self.prev_coord, coord);
+        let eraser_line_f = eraser_line.map_scalars(f64::from);
+
+        let cut = |id: PathId, path: &RenderablePath| {
+            let pieces = path.get().get().cut(eraser_line_f, radius)?;
+            Some(
+                (pieces.into_iter())
+                    .filter_map(RenderablePath::new)
+                    .map(move |piece| (id, piece)),
+            )
+        };
+        self.pieces = (self.pieces.drain(..))
+            .flat_map(|(id, piece)| match cut(id, &piece) {
+                Some(pieces) => pieces.collect(),
+                None => vec![(id, piece)],
+            })
+            .collect();
+        let margin = EraserRadius::MAX.get() as i32 + Width::MAX.half_ceil();
+        let ids = (model
+            .bounding_tile_items(utils::expand_rect(eraser_line.bounding_rect(), margin)))
+        .map(|(id, _)| id)
+        .filter(|id| !self.cut_path_ids.contains(id))
+        .collect::<FxHashSet<_>>();
+        for id in ids {
+            if let Some(pieces) = cut(id, model.path(id)) {
+                self.pieces.extend(pieces);
+                self.cut_path_ids.insert(id);
+                model.hide_path(id);
+            }
+        }
+
+        model.temp_layer().clear();
+        for (_, piece) in &self.pieces {
+            model.temp_layer().render_path(piece);
+        }
+        model
+            .temp_layer()
+            .render_eraser(coord, model.eraser_radius());
+        self.prev_coord = coord;
+    }
+
+    fn finish(self, mut model: model::DeferCommit) {
+        model.temp_layer().clear();
+        if self.cut_path_ids.is_empty() {
+            return;
+        }
+        model.cut_paths(self.cut_path_ids, self.pieces);
+    }
+}
+
 #[derive(Debug)]
 pub struct Controller {
     active_handler: Option<AnyHandler>,
+    /// The last position of the pointer on the board, where clips are pasted.
+    pointer_coord: Option<OnScreen<Coordinate<i32>>>,
     model: Model,
 }
 
 impl Controller {
+    /// The zoom factor applied by one click of the zoom buttons.
+    const ZOOM_STEP: f64 = 1.25;
+    /// The exponent of the zoom factor per pixel of wheel scrolling.
+    const ZOOM_SPEED: f64 = 0.002;
+    /// The distance on the board by which the arrow keys move the selected paths.
+    const NUDGE_STEP: i32 = 1;
+    /// [`NUDGE_STEP`](Self::NUDGE_STEP) with the shift key.
+    const NUDGE_STEP_LARGE: i32 = 10;
+
     pub fn new(model: Model) -> Self {
         Self {
             active_handler: None,
+            pointer_coord: None,
             model,
         }
     }
@@ -257,11 +610,240 @@ impl Controller {
         self.model.defer_commit().set_pen_color(color);
     }
 
+    pub fn add_pen_color_to_palette(&mut self) {
+        self.model.defer_commit().add_pen_color_to_palette();
+    }
+
+    pub fn remove_color_from_palette(&mut self, color: Color) {
+        self.model.defer_commit().remove_color_from_palette(color);
+    }
+
+    pub fn set_pen_width(&mut self, width: Width) {
+        self.model.defer_commit().set_pen_width(width);
+    }
+
+    pub fn set_lasso_mode(&mut self, mode: LassoMode) {
+        self.model.defer_commit().set_lasso_mode(mode);
+    }
+
+    pub fn set_eraser_radius(&mut self, radius: EraserRadius) {
+        self.model.defer_commit().set_eraser_radius(radius);
+    }
+
+    pub fn set_font_size(&mut self, size: FontSize) {
+        self.model.defer_commit().set_font_size(size);
+    }
+
+    pub fn finish_text_editing(&mut self) {
+        self.model.defer_commit().finish_text_editing();
+    }
+
+    /// Switches to the selector unless a selector is used, so that the selection is kept.
+    fn use_selector(model: &mut Model) {
+        if !model.tool().is_selector() {
+            model.set_tool(Tool::Selector);
+        }
+    }
+
+    pub fn select_all_paths(&mut self) {
+        let mut model = self.model.defer_commit();
+        Self::use_selector(&mut model);
+        model.select_all_paths();
+    }
+
+    pub fn select_paths_of_same_color(&mut self) {
+        let mut model = self.model.defer_commit();
+        Self::use_selector(&mut model);
+        model.select_paths_of_same_color();
+    }
+
+    pub fn invert_selection(&mut self) {
+        let mut model = self.model.defer_commit();
+        Self::use_selector(&mut model);
+        model.invert_selection();
+    }
+
+    pub fn select_paths_on_screen(&mut self) {
+        let mut model = self.model.defer_commit();
+        Self::use_selector(&mut model);
+        model.select_paths_on_screen();
+    }
+
+    pub fn group_selected_paths(&mut self) {
+        self.model.defer_commit().group_selected_paths();
+    }
+
+    pub fn ungroup_selected_paths(&mut self) {
+        self.model.defer_commit().ungroup_selected_paths();
+    }
+
+    pub fn lock_selected_paths(&mut self) {
+        self.model.defer_commit().lock_selected_paths();
+    }
+
+    pub fn unlock_all_paths(&mut self) {
+        if self.active_handler.is_some() {
+            return;
+        }
+        let mut model = self.model.defer_commit();
+        Self::use_selector(&mut model);
+        model.unlock_all_paths();
+    }
+
+    pub fn restack_selected_paths(&mut self, restack: model::Restack) {
+        self.model.defer_commit().restack_selected_paths(restack);
+    }
+
+    pub fn boards(&self) -> &Boards {
+        self.model.boards()
+    }
+
+    pub fn switch_board(&mut self, id: BoardId) {
+        if self.active_handler.is_some() {
+            return;
+        }
+        self.model.defer_commit().switch_board(id);
+    }
+
+    pub fn add_board(&mut self) {
+        if self.active_handler.is_some() {
+            return;
+        }
+        self.model.defer_commit().add_board();
+    }
+
+    pub fn rename_board(&mut self, id: BoardId, name: String) {
+        self.model.defer_commit().rename_board(id, name);
+    }
+
+    pub fn duplicate_board(&mut self, id: BoardId) {
+        if self.active_handler.is_some() {
+            return;
+        }
+        self.model.defer_commit().duplicate_board(id);
+    }
+
+    pub fn remove_board(&mut self, id: BoardId) {
+        if self.active_handler.is_some() {
+            return;
+        }
+        self.model.defer_commit().remove_board(id);
+    }
+
+    pub fn doc_layers(&self) -> &DocLayers {
+        self.model.doc_layers()
+    }
+
+    pub fn add_doc_layer(&mut self) {
+        self.model.defer_commit().add_doc_layer();
+    }
+
+    pub fn remove_doc_layer(&mut self, id: DocLayerId) {
+        if self.active_handler.is_some() {
+            return;
+        }
+        self.model.defer_commit().remove_doc_layer(id);
+    }
+
+    pub fn set_active_doc_layer(&mut self, id: DocLayerId) {
+        self.model.defer_commit().set_active_doc_layer(id);
+    }
+
+    pub fn rename_doc_layer(&mut self, id: DocLayerId, name: String) {
+        self.model.defer_commit().rename_doc_layer(id, name);
+    }
+
+    pub fn set_doc_layer_visible(&mut self, id: DocLayerId, visible: bool) {
+        self.model.defer_commit().set_doc_layer_visible(id, visible);
+    }
+
+    pub fn set_doc_layer_locked(&mut self, id: DocLayerId, locked: bool) {
+        self.model.defer_commit().set_doc_layer_locked(id, locked);
+    }
+
+    pub fn move_doc_layer(&mut self, id: DocLayerId, up: bool) {
+        self.model.defer_commit().move_doc_layer(id, up);
+    }
+
+    pub fn move_selected_paths_to_doc_layer(&mut self, id: DocLayerId) {
+        self.model
+            .defer_commit()
+            .move_selected_paths_to_doc_layer(id);
+    }
+
+    pub fn export_paths(&self, scope: model::ExportScope) -> Option<(Clip, Rect<i32>)> {
+        self.model.export_paths(scope)
+    }
+
+    pub fn document(&self) -> Document {
+        self.model.document()
+    }
+
+    pub fn open_document(&mut self, doc: Document, merge: bool) {
+        if self.active_handler.is_some() {
+            return;
+        }
+        self.model.defer_commit().open_document(doc, merge);
+    }
+
+    pub fn copy(&self) -> Option<Clip> {
+        self.model.copy_selected_paths()
+    }
+
+    pub fn cut(&mut self) -> Option<Clip> {
+        if self.active_handler.is_some() {
+            return None;
+        }
+        let mut model = self.model.defer_commit();
+        let clip = model.copy_selected_paths();
+        model.remove_selected_paths();
+        clip
+    }
+
+    pub fn paste(&mut self, clip: Clip) {
+        if self.active_handler.is_some() {
+            return;
+        }
+        self.model
+            .defer_commit()
+            .paste_clip(clip, self.pointer_coord);
+    }
+
+    /// Inserts the paths at `coord`, or the pointer if [`None`].
+    pub fn import_paths(&mut self, paths: Vec<Path>, coord: Option<OnScreen<Coordinate<i32>>>) {
+        if self.active_handler.is_some() {
+            return;
+        }
+        self.model
+            .defer_commit()
+            .import_paths(paths, coord.or(self.pointer_coord));
+    }
+
+    pub fn insert_image(
+        &mut self,
+        data: ImageData,
+        size: Coordinate<u32>,
+        coord: Option<OnScreen<Coordinate<i32>>>,
+    ) {
+        self.model.defer_commit().insert_image(data, size, coord);
+    }
+
     pub fn clear_paths(&mut self) {
         self.model.defer_commit().clear_paths();
     }
 
-    pub fn on_key_down(&mut self, event: web::KeyboardEvent) {
+    pub fn zoom_in(&mut self) {
+        self.model.defer_commit().zoom(Self::ZOOM_STEP, None);
+    }
+
+    pub fn zoom_out(&mut self) {
+        self.model
+            .defer_commit()
+            .zoom(Self::ZOOM_STEP.recip(), None);
+    }
+
+    /// Returns whether the key is handled, in which case the browser's default action is prevented.
+    pub fn on_key_down(&mut self, event: web::KeyboardEvent) -> bool {
         let mut model = self.model.defer_commit();
         match event.key.as_str() {
             "Delete" => {
@@ -273,14 +855,83 @@ impl Controller {
             "y" if event.ctrl_key => {
                 model.redo();
             }
-            _ => {}
+            "d" if event.ctrl_key => {
+                model.duplicate_selected_paths();
+            }
+            "a" if event.ctrl_key && event.alt_key => {
+                Self::use_selector(&mut model);
+                model.select_paths_on_screen();
+            }
+            "a" if event.ctrl_key => {
+                Self::use_selector(&mut model);
+                model.select_all_paths();
+            }
+            "e" if event.ctrl_key => {
+                Self::use_selector(&mut model);
+                model.select_paths_of_same_color();
+            }
+            "i" if event.ctrl_key => {
+                Self::use_selector(&mut model);
+                model.invert_selection();
+            }
+            "g" if event.ctrl_key => {
+                model.group_selected_paths();
+            }
+            "G" if event.ctrl_key => {
+                model.ungroup_selected_paths();
+            }
+            "l" if event.ctrl_key => {
+                model.lock_selected_paths();
+            }
+            "L" if event.ctrl_key => {
+                if self.active_handler.is_some() {
+                    return true;
+                }
+                Self::use_selector(&mut model);
+                model.unlock_all_paths();
+            }
+            "]" if event.ctrl_key => {
+                model.restack_selected_paths(model::Restack::Forward);
+            }
+            "[" if event.ctrl_key => {
+                model.restack_selected_paths(model::Restack::Backward);
+            }
+            "}" if event.ctrl_key => {
+                model.restack_selected_paths(model::Restack::ToFront);
+            }
+            "{" if event.ctrl_key => {
+                model.restack_selected_paths(model::Restack::ToBack);
+            }
+            "ArrowLeft" | "ArrowRight" | "ArrowUp" | "ArrowDown" => {
+                if self.active_handler.is_some() {
+                    return true;
+                }
+                let step = if event.shift_key {
+                    Self::NUDGE_STEP_LARGE
+                } else {
+                    Self::NUDGE_STEP
+                };
+                let delta = match event.key.as_str() {
+                    "ArrowLeft" => Coordinate { x: -step, y: 0 },
+                    "ArrowRight" => Coordinate { x: step, y: 0 },
+                    "ArrowUp" => Coordinate { x: 0, y: -step },
+                    _ => Coordinate { x: 0, y: step },
+                };
+                model.nudge_selected_paths(delta);
+            }
+            _ => return false,
         }
+        true
     }
 
     pub fn on_wheel(&mut self, event: web::WheelEvent) {
         let mut model = self.model.defer_commit();
-        let delta = model.delta_of(event.delta.map(|d| -d));
-        model.scroll(delta);
+        if event.ctrl_key {
+            let factor = (-f64::from(event.delta.0.y) * Self::ZOOM_SPEED).exp();
+            model.zoom(factor, Some(event.coord));
+        } else {
+            model.scroll(event.delta.map(|d| -d));
+        }
     }
 
     pub fn on_pointer_down(&mut self, event: web::MouseEvent) {
@@ -288,20 +939,39 @@ impl Controller {
             return;
         }
         let mut model = self.model.defer_commit();
+        if model.is_editing_text() {
+            // the click only finishes editing
+            model.finish_text_editing();
+            return;
+        }
         match event.button {
             web::MouseButton::Left => {
-                if model.selected_paths().any(|(_, path)| {
+                if let Some(handle) = model.handle_at(model.coord_at(event.coord)) {
+                    let mut handler = TransformHandler::new(model, handle, event.coord);
+                    handler.set_constrained(event.shift_key);
+                    self.active_handler = Some(handler.into());
+                } else if model.selected_paths().any(|(_, path)| {
                     path.bounding_rect()
                         .get()
                         .contains(&model.coord_at(event.coord))
                 }) {
-                    self.active_handler = Some(ShiftHandler::new(model, event.coord).into());
+                    self.active_handler =
+                        Some(ShiftHandler::new(model, event.coord, event.alt_key).into());
                 } else {
                     model.unselect_all_paths();
                     self.active_handler = Some(match model.tool() {
                         Tool::Selector => SelectHandler::new(&*model, event.coord).into(),
+                        Tool::Lasso => LassoHandler::new(&*model, event.coord).into(),
                         Tool::Pen => DrawHandler::new(&*model, event.coord).into(),
                         Tool::Eraser => EraseHandler::new(&*model, event.coord).into(),
+                        Tool::Text => TextHandler::new(&*model, event.coord).into(),
+                        Tool::PartialEraser => {
+                            PartialEraseHandler::new(&*model, event.coord).into()
+                        }
+                        tool @ (Tool::Line | Tool::Arrow | Tool::Rectangle | Tool::Ellipse) => {
+                            let shape = tool.shape().expect("not a shape tool");
+                            ShapeHandler::new(&*model, shape, event.coord).into()
+                        }
                     });
                 }
             }
@@ -313,6 +983,10 @@ impl Controller {
     }
 
     pub fn on_pointer_move(&mut self, event: web::MouseEvent) {
+        self.pointer_coord = Some(event.coord);
+        if let Some(AnyHandler::Transform(h)) = &mut self.active_handler {
+            h.set_constrained(event.shift_key);
+        }
         if let Some(h) = &mut self.active_handler {
             h.move_to(self.model.defer_commit(), event.coord);
         }
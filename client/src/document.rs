@@ -0,0 +1,55 @@
+//! The `.papirs` file format, which holds a board by itself.
+
+use crate::{
+    common::{ImageData, ImageHash, Path, PathId},
+    web,
+};
+use anyhow::{bail, Result};
+use rustc_hash::FxHashMap;
+use serde::{Deserialize, Serialize};
+
+/// The bytes which every `.papirs` file starts with.
+const MAGIC: &[u8; 8] = b"\x89PAPIRS\n";
+
+/// The version of the current format, stored next to the magic bytes as a little-endian `u32`.
+const VERSION: u32 = 1;
+
+/// The content of a `.papirs` file.
+#[derive(Clone, Default, Debug, Serialize, Deserialize)]
+pub struct Document {
+    pub paths: FxHashMap<PathId, Path>,
+    /// The stacking orders of the paths, where the greater ones are rendered above.
+    pub path_orders: FxHashMap<PathId, i64>,
+    /// The images which the paths refer to.
+    pub images: FxHashMap<ImageHash, ImageData>,
+}
+
+impl Document {
+    pub const EXTENSION: &'static str = "papirs";
+    pub const MIME_TYPE: &'static str = "application/vnd.papirs";
+
+    /// Encodes the document into the content of a file.
+    pub fn encode(&self) -> Result<Vec<u8>> {
+        let mut bytes = MAGIC.to_vec();
+        bytes.extend(VERSION.to_le_bytes());
+        web::encode_bytes_into(self, &mut bytes)?;
+        Ok(bytes)
+    }
+
+    /// Decodes the content of a file made by [`Self::encode`].
+    pub fn decode(bytes: &[u8]) -> Result<Self> {
+        let bytes = match bytes.strip_prefix(MAGIC) {
+            Some(bytes) => bytes,
+            None => bail!("not a papirs file"),
+        };
+        if bytes.len() < 4 {
+            bail!("truncated file");
+        }
+        let (version, bytes) = bytes.split_at(4);
+        let version = u32::from_le_bytes(version.try_into().expect("4 bytes"));
+        if version != VERSION {
+            bail!("unknown file version: {}", version);
+        }
+        Ok(web::decode_bytes(bytes)?)
+    }
+}
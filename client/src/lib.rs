@@ -1,11 +1,16 @@
 mod common;
 mod ctrl;
+mod document;
 mod model;
+mod raster;
+mod storage;
+mod svg;
 mod utils;
 mod view;
 mod web;
 
-use crate::{ctrl::Controller, model::Model, view::View};
+use crate::{ctrl::Controller, model::Model, storage::Storage, view::View};
+use std::rc::Rc;
 use wasm_bindgen::prelude::*;
 
 #[wasm_bindgen(start)]
@@ -19,10 +24,35 @@ pub fn start() {
     };
     console_log::init_with_level(log_level).expect("failed to initialize log");
 
-    let storage = web::Storage::local().expect("no local storage");
-    let view = View::init();
-    let model = Model::load(storage, view.clone());
-    let ctrl = Controller::new(model);
+    wasm_bindgen_futures::spawn_local(async {
+        let view = View::init();
+        let storage = open_storage(&view).await;
+        let model = Model::load(storage, view.clone());
+        let ctrl = Controller::new(model);
 
-    view.listen_events(ctrl);
+        view.listen_events(ctrl);
+    });
+}
+
+/// Opens IndexedDB, or falls back to `localStorage` (or memory, where nothing is saved)
+/// if unavailable.
+///
+/// The view warns the user if the changes fail to be saved.
+async fn open_storage(view: &View) -> Rc<dyn Storage> {
+    let on_error = {
+        let view = view.clone();
+        move |cause: String| view.warn_save_failure(&cause)
+    };
+    match web::IndexedDbStorage::open(on_error).await {
+        Ok(storage) => return Rc::new(storage),
+        Err(err) => log::warn!("IndexedDB unavailable, using localStorage instead: {}", err),
+    }
+    match web::LocalStorage::new() {
+        Some(storage) => Rc::new(storage),
+        None => {
+            log::warn!("no local storage; nothing will be saved");
+            view.warn_save_failure("no storage available");
+            Rc::new(storage::MemoryStorage::default())
+        }
+    }
 }
@@ -2,21 +2,32 @@
 
 mod compat;
 mod history;
+mod path_store;
+mod persisted;
 mod recorder;
 mod tiling;
 
-use self::{history::History, recorder::Recorder, tiling::Tiling};
+use self::{
+    history::History, path_store::PathStore, persisted::Persisted, recorder::Recorder,
+    tiling::Tiling,
+};
 use crate::{
-    common::{Color, OnScreen, PathId, RenderablePath, Tool},
-    utils,
+    common::{
+        Board, BoardId, Boards, Clip, Color, DocLayer, DocLayerId, DocLayers, EraserRadius,
+        FontSize, GroupId, ImageData, ImageHash, LassoMode, OnScreen, Palette, Path, PathId,
+        RenderablePath, Scale, Shape, Text, Tool, Width,
+    },
+    document::Document,
+    storage::Storage,
+    utils::{self, Affine, MapScalars as _},
     view::{Layer, LayerHandle, View},
-    web,
 };
 use derive_more::{Deref, DerefMut};
-use geo::{prelude::*, Coordinate, Line, Rect};
+use geo::{prelude::*, Coordinate, Line, LineString, Point, Polygon, Rect};
+use itertools::Itertools as _;
 use rustc_hash::{FxHashMap, FxHashSet};
 use serde::{Deserialize, Serialize};
-use std::mem;
+use std::{iter, mem, rc::Rc};
 
 #[derive(Debug)]
 enum Command {
@@ -30,28 +41,183 @@ enum Command {
     Remove {
         paths: Vec<(PathId, RenderablePath)>,
     },
+    Replace {
+        paths: Vec<(PathId, RenderablePath)>,
+    },
+    Transform {
+        /// The coordinates of the paths before the transformation.
+        paths: Vec<(PathId, LineString<i32>)>,
+        transform: Affine,
+    },
+    Restack {
+        /// The stacking orders of the paths before restacking.
+        orders: Vec<(PathId, i64)>,
+    },
+    Regroup {
+        /// The groups of the paths before regrouping.
+        groups: Vec<(PathId, Option<GroupId>)>,
+    },
+    /// Commands performed at once, in order.
+    Batch {
+        commands: Vec<Command>,
+    },
+}
+
+/// A handle shown around the selected paths to transform them.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Handle {
+    /// A handle at the corner of the given index in [`utils::rect_corners`], which scales.
+    Scale(usize),
+    /// A handle above the top edge, which rotates.
+    Rotate,
+}
+
+/// A move of paths in the stacking order within their layers.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum Restack {
+    Forward,
+    Backward,
+    ToFront,
+    ToBack,
+}
+
+/// Which paths to export.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
+pub enum ExportScope {
+    /// All the paths in the visible layers.
+    Board,
+    Selection,
+    /// The paths in the visible layers on the screen, cut off at its edges.
+    Viewport,
+}
+
+/// The stacking orders free beneath and above those of the paths.
+#[derive(Debug)]
+struct FreeOrders {
+    bottom: i64,
+    top: i64,
+}
+
+impl FreeOrders {
+    fn new(orders: &FxHashMap<PathId, i64>) -> Self {
+        Self {
+            bottom: orders.values().min().map_or(0, |&min| min - 1),
+            top: orders.values().max().map_or(0, |&max| max + 1),
+        }
+    }
+
+    /// Takes the order above the others, or beneath them for a new image.
+    fn take(&mut self, is_background: bool) -> i64 {
+        if is_background {
+            self.bottom -= 1;
+            self.bottom + 1
+        } else {
+            self.top += 1;
+            self.top - 1
+        }
+    }
+}
+
+/// The layers and the orders which the paths are stacked by.
+#[derive(Clone, Copy, Debug)]
+struct Stacking<'a> {
+    doc_layers: &'a DocLayers,
+    path_layers: &'a FxHashMap<PathId, DocLayerId>,
+    path_orders: &'a FxHashMap<PathId, i64>,
+}
+
+impl Stacking<'_> {
+    /// Returns the key by which the paths are sorted from the bottom to the top.
+    fn key(self, id: PathId) -> (Option<usize>, i64, PathId) {
+        let layer = (self.doc_layers).resolve(self.path_layers.get(&id).copied());
+        let order = self.path_orders.get(&id).copied().unwrap_or_default();
+        (self.doc_layers.position(layer.id), order, id)
+    }
+
+    /// Sorts the paths from the bottom to the top.
+    fn sort(self, ids: impl IntoIterator<Item = PathId>) -> Vec<PathId> {
+        (ids.into_iter())
+            .sorted_by_key(|&id| self.key(id))
+            .collect()
+    }
+}
+
+/// A text being edited in the text editor.
+#[derive(Debug)]
+struct TextEditing {
+    /// The ID of the text being edited, or [`None`] if it is a new one.
+    id: Option<PathId>,
+    /// The top-left corner of the text on the board.
+    coord: Coordinate<i32>,
+    color: Color,
+    font_size: FontSize,
 }
 
 #[derive(Debug)]
 pub struct Model {
+    /// The boards in the storage, the active one of which is loaded into the other fields.
+    boards: Recorder<Boards>,
     paths: Recorder<FxHashMap<PathId, RenderablePath>>,
+    /// The paths in the storage, which are saved one by one as changed.
+    path_store: PathStore,
+    /// The layers which the paths belong to.
+    ///
+    /// Removed paths are kept here to be restored into the same layer by undoing,
+    /// and pruned on the next load.
+    path_layers: Recorder<FxHashMap<PathId, DocLayerId>>,
+    doc_layers: Recorder<DocLayers>,
+    /// The stacking orders of the paths, where the greater ones are rendered above in a layer.
+    ///
+    /// Removed paths are kept here as well as `path_layers`.
+    path_orders: Recorder<FxHashMap<PathId, i64>>,
+    /// The groups of the grouped paths.
+    ///
+    /// Removed paths are kept here as well as `path_layers`.
+    path_groups: Recorder<FxHashMap<PathId, GroupId>>,
+    /// The paths which cannot be selected nor erased.
+    ///
+    /// Removed paths are kept here as well as `path_layers`.
+    locked_path_ids: Recorder<FxHashSet<PathId>>,
     tiling: Tiling,
     history: History<Command>,
 
     selected_path_ids: Recorder<FxHashSet<PathId>>,
     hidden_path_ids: Recorder<FxHashSet<PathId>>,
+    /// The position of the board's origin on the screen.
     offset: Recorder<Coordinate<i32>>,
+    scale: Recorder<Scale>,
     tool: Recorder<Tool>,
     pen_color: Recorder<Color>,
+    pen_width: Recorder<Width>,
+    palette: Recorder<Palette>,
+    font_size: Recorder<FontSize>,
+    eraser_radius: Recorder<EraserRadius>,
+    lasso_mode: Recorder<LassoMode>,
+    editing_text: Option<TextEditing>,
 
-    storage: web::Storage,
+    storage: Rc<dyn Storage>,
     view: View,
 }
 
 impl Model {
-    fn load_field<T>(storage: &web::Storage, key: &str) -> T
+    /// The radius of the handles on the screen, in pixels.
+    const HANDLE_RADIUS: f64 = 5.;
+    /// The distance of the rotation handle from the selection on the screen, in pixels.
+    const ROTATE_HANDLE_DISTANCE: f64 = 24.;
+    /// The distance on the board between duplicated paths and their originals.
+    const DUPLICATE_OFFSET: Coordinate<i32> = Coordinate { x: 16, y: 16 };
+
+    fn load_field<T>(storage: &dyn Storage, key: &str) -> T
     where
         T: for<'de> Deserialize<'de> + Default,
+    {
+        Self::load_optional_field(storage, key).unwrap_or_default()
+    }
+
+    /// Returns [`None`] if the field is not found in the storage or fails to be loaded.
+    fn load_optional_field<T>(storage: &dyn Storage, key: &str) -> Option<T>
+    where
+        T: for<'de> Deserialize<'de>,
     {
         storage
             .get(&format!("papirs:{}", key))
@@ -60,94 +226,179 @@ impl Model {
                 log::error!("`{}` found in storage but failed to load: {}", key, err);
                 None
             })
-            .unwrap_or_default()
     }
 
-    fn save_field<T>(storage: &web::Storage, key: &str, value: &T)
+    /// Returns whether succeeded, where a failure is logged.
+    fn save_field<T>(storage: &dyn Storage, key: &str, value: &T) -> bool
     where
         T: Serialize,
     {
         if let Err(err) = storage.set(&format!("papirs:{}", key), value) {
             log::error!("failed to save `{}`: {}", key, err);
+            return false;
         }
+        true
     }
 
-    pub fn load(storage: web::Storage, mut view: View) -> Self {
-        macro_rules! load {
-            ($field:ident) => {
-                Self::load_field(&storage, stringify!($field))
-            };
-        }
+    /// Returns the key of the field of the board, which is passed to [`Self::load_field`] and
+    /// [`Self::save_field`].
+    pub(super) fn board_key(board: BoardId, field: &str) -> String {
+        format!("board:{}:{}", board, field)
+    }
 
-        let old_data = compat::Data::load_and_remove(&storage);
-        let needs_to_save = old_data.is_some();
+    fn contains_field(storage: &dyn Storage, key: &str) -> bool {
+        storage.contains(&format!("papirs:{}", key))
+    }
 
-        let (paths, offset, tool, pen_color) = old_data.map_or_else(
-            || (load!(paths), load!(offset), load!(tool), load!(pen_color)),
-            |data| (data.paths, data.offset, data.tool, data.pen_color),
+    pub fn load(storage: Rc<dyn Storage>, mut view: View) -> Self {
+        let Persisted {
+            boards,
+            paths,
+            path_store,
+            path_layers,
+            doc_layers,
+            path_orders,
+            path_groups,
+            locked_path_ids,
+            offset,
+            scale,
+            tool,
+            pen_color,
+            pen_width,
+            palette,
+            font_size,
+            eraser_radius,
+            lasso_mode,
+            images,
+        } = Persisted::load(&*storage);
+        let paths = Recorder::new(
+            (paths.into_iter())
+                .filter_map(|(id, path)| Some((id, RenderablePath::new(path)?)))
+                .collect::<FxHashMap<_, _>>(),
         );
-        let tiling = (paths.get().iter())
-            .map(|(&id, path)| (id, &path.get().get().coords))
-            .collect();
+        let tiling = (paths.get().iter()).map(|(&id, path)| (id, path)).collect();
 
-        view.translate(*offset.get());
-        for path in paths.get().values() {
-            view.layers[Layer::Main].render_path(path);
+        for (&hash, data) in &images {
+            view.register_image(hash, data);
         }
+        view.transform(OnScreen(*offset.get()), *scale.get());
         view.select_tool(*tool.get());
+        view.render_palette(palette.get());
         view.select_pen_color(*pen_color.get());
+        view.select_pen_width(*pen_width.get());
+        view.select_font_size(*font_size.get());
+        view.select_eraser_radius(*eraser_radius.get());
+        view.select_lasso_mode(*lasso_mode.get());
+        view.render_doc_layers(doc_layers.get());
+        view.render_boards(boards.get());
 
         let model = Self {
+            boards,
             paths,
+            path_store,
+            path_layers,
+            doc_layers,
+            path_orders,
+            path_groups,
+            locked_path_ids,
             tiling,
             history: Default::default(),
 
             selected_path_ids: Default::default(),
             hidden_path_ids: Default::default(),
             offset,
+            scale,
             tool,
             pen_color,
+            pen_width,
+            palette,
+            font_size,
+            eraser_radius,
+            lasso_mode,
+            editing_text: None,
 
             storage,
             view,
         };
-        if needs_to_save {
-            model.force_save();
-        }
+        model.rerender_main_layer();
+        model.rerender_sub_layer();
         model
     }
 
-    fn force_save(&self) {
+    fn image_key(hash: ImageHash) -> String {
+        format!("image:{}", hash)
+    }
+
+    fn save(&mut self) {
         macro_rules! save {
             ($field:ident) => {
-                Self::save_field(&self.storage, stringify!($field), &self.$field);
+                if self.$field.is_updated() {
+                    Self::save_field(&*self.storage, stringify!($field), &self.$field);
+                }
             };
         }
-        save!(paths);
-        save!(offset);
-        save!(tool);
-        save!(pen_color);
-    }
-
-    fn save(&self) {
-        macro_rules! save {
+        macro_rules! save_board {
             ($field:ident) => {
                 if self.$field.is_updated() {
-                    Self::save_field(&self.storage, stringify!($field), &self.$field);
+                    let key = self.board_key_of(stringify!($field));
+                    Self::save_field(&*self.storage, &key, &self.$field);
                 }
             };
         }
-        save!(paths);
-        save!(offset);
+        save!(boards);
+        self.path_store.save(&*self.storage, self.paths.get());
+        save_board!(offset);
+        save_board!(scale);
         save!(tool);
         save!(pen_color);
+        save!(pen_width);
+        save!(palette);
+        save!(font_size);
+        save!(eraser_radius);
+        save!(lasso_mode);
+        save_board!(doc_layers);
+        save_board!(path_layers);
+        save_board!(path_orders);
+        save_board!(path_groups);
+        save_board!(locked_path_ids);
     }
 
-    pub fn bounding_tile_items(
-        &self,
-        geo: impl BoundingRect<i32, Output = Rect<i32>> + Intersects<Rect<i32>>,
-    ) -> impl Iterator<Item = (PathId, &[Line<i32>])> {
-        self.tiling.bounding_tile_items(geo)
+    /// Returns the key of the field of the active board.
+    fn board_key_of(&self, field: &str) -> String {
+        Self::board_key(self.boards.get().active, field)
+    }
+
+    /// Returns the paths registered to the tiles which `geo` intersects,
+    /// except ones which cannot be selected or erased.
+    pub fn bounding_tile_items<G>(&self, geo: G) -> impl Iterator<Item = (PathId, &[Line<i32>])>
+    where
+        G: BoundingRect<i32> + Intersects<Rect<i32>>,
+        G::Output: Into<Option<Rect<i32>>>,
+    {
+        (self.tiling.bounding_tile_items(geo)).filter(move |&(id, _)| self.is_editable(id))
+    }
+
+    /// Returns the layer which the path belongs to.
+    fn layer_of(&self, id: PathId) -> &DocLayer {
+        (self.doc_layers.get()).resolve(self.path_layers.get().get(&id).copied())
+    }
+
+    fn stacking(&self) -> Stacking<'_> {
+        Stacking {
+            doc_layers: self.doc_layers.get(),
+            path_layers: self.path_layers.get(),
+            path_orders: self.path_orders.get(),
+        }
+    }
+
+    /// Returns the key by which the paths are sorted from the bottom to the top.
+    fn stack_key(&self, id: PathId) -> (Option<usize>, i64, PathId) {
+        self.stacking().key(id)
+    }
+
+    /// Returns whether the path can be selected or erased.
+    fn is_editable(&self, id: PathId) -> bool {
+        self.layer_of(id).is_editable() && !self.locked_path_ids.get().contains(&id)
     }
 
     pub fn path(&self, id: PathId) -> &RenderablePath {
@@ -164,9 +415,24 @@ impl Model {
             p.reserve(paths.size_hint().0);
             false
         });
+        let active = self.doc_layers.get().active;
+        let mut free_orders = FreeOrders::new(self.path_orders.get());
         let ids = paths
             .map(|(id, path)| {
-                self.tiling.insert_path(id, &path.get().get().coords);
+                // a restored path goes back into its layer and order
+                self.path_layers.update(|l| {
+                    let prev_len = l.len();
+                    l.entry(id).or_insert(active);
+                    l.len() != prev_len
+                });
+                self.path_orders.update(|o| {
+                    let prev_len = o.len();
+                    o.entry(id)
+                        .or_insert_with(|| free_orders.take(path.is_background()));
+                    o.len() != prev_len
+                });
+                self.tiling.insert_path(id, &path);
+                self.path_store.mark_changed(id);
                 let old = self.paths.get_mut().insert(id, path);
                 assert!(old.is_none(), "path already exists");
                 id
@@ -176,7 +442,53 @@ impl Model {
     }
 
     pub fn shift_paths(&mut self, ids: impl IntoIterator<Item = PathId>, delta: Coordinate<i32>) {
-        let ids = (ids.into_iter())
+        let ids = self.translate_paths(ids, delta);
+        self.history.push(Command::Shift {
+            path_ids: ids,
+            delta,
+        });
+    }
+
+    /// Shifts the selected paths, which is undone at once with the consecutive calls.
+    pub fn nudge_selected_paths(&mut self, delta: Coordinate<i32>) {
+        if self.selected_path_ids.get().is_empty() {
+            return;
+        }
+        let ids = self
+            .selected_path_ids
+            .get()
+            .iter()
+            .copied()
+            .collect::<Vec<_>>();
+        let ids = self.translate_paths(ids, delta);
+        let com = Command::Shift {
+            path_ids: ids,
+            delta,
+        };
+        self.history
+            .push_merging(com, |last, com| match (last, com) {
+                (
+                    Command::Shift { path_ids, delta },
+                    Command::Shift {
+                        path_ids: new_ids,
+                        delta: new_delta,
+                    },
+                ) if path_ids.len() == new_ids.len()
+                    && new_ids.iter().collect::<FxHashSet<_>>() == path_ids.iter().collect() =>
+                {
+                    *delta = *delta + new_delta;
+                    Ok(())
+                }
+                (_, com) => Err(com),
+            });
+    }
+
+    fn translate_paths(
+        &mut self,
+        ids: impl IntoIterator<Item = PathId>,
+        delta: Coordinate<i32>,
+    ) -> Vec<PathId> {
+        (ids.into_iter())
             .map(|id| {
                 let path = self.paths.get_mut().get_mut(&id).expect("path not found");
                 *path = {
@@ -185,14 +497,11 @@ impl Model {
                     RenderablePath::new(path).expect("`path` should not be empty")
                 };
                 self.tiling.remove_path(id);
-                self.tiling.insert_path(id, &path.get().get().coords);
+                self.tiling.insert_path(id, path);
+                self.path_store.mark_changed(id);
                 id
             })
-            .collect();
-        self.history.push(Command::Shift {
-            path_ids: ids,
-            delta,
-        });
+            .collect()
     }
 
     pub fn remove_paths(&mut self, ids: impl IntoIterator<Item = PathId>) {
@@ -201,6 +510,7 @@ impl Model {
                 self.tiling.remove_path(id);
                 self.selected_path_ids.update(|s| s.remove(&id));
                 self.hidden_path_ids.update(|h| h.remove(&id));
+                self.path_store.mark_changed(id);
                 let path = self.paths.get_mut().remove(&id).expect("path not found");
                 (id, path)
             })
@@ -208,6 +518,489 @@ impl Model {
         self.history.push(Command::Remove { paths });
     }
 
+    /// Saves the image data unless already stored, and registers it with the view.
+    fn store_image(&mut self, data: &ImageData) -> ImageHash {
+        let hash = data.hash();
+        let key = Self::image_key(hash);
+        // the same image may be already stored
+        if !Self::contains_field(&*self.storage, &key) {
+            Self::save_field(&*self.storage, &key, data);
+        }
+        self.view.register_image(hash, data);
+        hash
+    }
+
+    /// Returns the coordinate on the board of `anchor`, or of the center of the screen if [`None`].
+    fn anchor_coord(&self, anchor: Option<OnScreen<Coordinate<i32>>>) -> Coordinate<i32> {
+        let anchor = anchor.unwrap_or_else(|| {
+            self.view
+                .size()
+                .map(|size| utils::coord_map_scalars(size, |s| s as i32 / 2))
+        });
+        self.coord_at(anchor)
+    }
+
+    /// Inserts the image of `size` centered at `anchor` (or the center of the screen if [`None`]),
+    /// shrinking it to fit in the screen.
+    pub fn insert_image(
+        &mut self,
+        data: ImageData,
+        size: Coordinate<u32>,
+        anchor: Option<OnScreen<Coordinate<i32>>>,
+    ) {
+        let hash = self.store_image(&data);
+        let board_rect = self.board_rect();
+        let size = utils::coord_map_scalars(size, f64::from);
+        let ratio = (f64::from(board_rect.width()) / size.x)
+            .min(f64::from(board_rect.height()) / size.y)
+            .min(1.);
+        let size = utils::coord_map_scalars(size, |s| ((s * ratio).round() as i32).max(1));
+        let start = self.anchor_coord(anchor) - size / 2;
+        let shape = Shape::Image(hash);
+        let path = Path {
+            color: Color::BLACK,
+            width: Width::MIN,
+            coords: shape.coords_between(start, start + size),
+            shape,
+        };
+        let path = RenderablePath::new(path).expect("`path` should not be empty");
+        self.insert_paths(iter::once((PathId::gen(), path)));
+    }
+
+    /// Returns the selected paths with the images they reference, or [`None`] if nothing selected.
+    pub fn copy_selected_paths(&self) -> Option<Clip> {
+        if self.selected_path_ids.get().is_empty() {
+            return None;
+        }
+        Some(self.clip_of(self.selected_path_ids.get().iter().copied()))
+    }
+
+    /// Returns the paths in the scope with the images which they refer to, and the rectangle
+    /// on the board to be exported, or [`None`] if no paths in it.
+    pub fn export_paths(&self, scope: ExportScope) -> Option<(Clip, Rect<i32>)> {
+        let ids = match scope {
+            ExportScope::Board => (self.paths.get().keys())
+                .copied()
+                .filter(|&id| self.layer_of(id).visible)
+                .collect::<Vec<_>>(),
+            ExportScope::Selection => self.selected_path_ids.get().iter().copied().collect(),
+            ExportScope::Viewport => (self.tiling.bounding_tile_items(self.board_rect()))
+                .map(|(id, _)| id)
+                .filter(|&id| self.layer_of(id).visible)
+                .unique()
+                .collect(),
+        };
+        let rect = match scope {
+            ExportScope::Board | ExportScope::Selection => (ids.iter())
+                .map(|&id| *self.path(id).bounding_rect().get())
+                .reduce(utils::rect_union)?,
+            ExportScope::Viewport if !ids.is_empty() => self.board_rect(),
+            ExportScope::Viewport => return None,
+        };
+        Some((self.clip_of(ids), rect))
+    }
+
+    /// Returns the board as a document.
+    pub fn document(&self) -> Document {
+        let paths = (self.paths.get().iter())
+            .map(|(&id, path)| (id, path.get().get().clone()))
+            .collect::<FxHashMap<_, _>>();
+        let path_orders = (self.path_orders.get().iter())
+            .filter(|(id, _)| paths.contains_key(id))
+            .map(|(&id, &order)| (id, order))
+            .collect();
+        let images = self.images_of(paths.values());
+        Document {
+            paths,
+            path_orders,
+            images,
+        }
+    }
+
+    /// Inserts the paths of the document into the active layer, replacing the editable ones
+    /// unless `merge`, which is undone at once.
+    pub fn open_document(&mut self, doc: Document, merge: bool) {
+        for data in doc.images.values() {
+            self.store_image(data);
+        }
+        let paths = (doc.paths.into_iter())
+            .filter(|(_, path)| !path.coords.0.is_empty())
+            .sorted_by_key(|&(id, _)| (doc.path_orders.get(&id).copied().unwrap_or_default(), id))
+            .map(|(_, path)| (PathId::gen(), path))
+            .collect::<Vec<_>>();
+        self.history.start_group();
+        if !merge {
+            self.clear_paths();
+        }
+        self.insert_copies(paths, Coordinate::zero());
+        self.history
+            .finish_group(|commands| Command::Batch { commands });
+    }
+
+    /// Returns the stored images which the paths refer to.
+    fn images_of<'a>(
+        &self,
+        paths: impl IntoIterator<Item = &'a Path>,
+    ) -> FxHashMap<ImageHash, ImageData> {
+        (paths.into_iter())
+            .filter_map(|path| match path.shape {
+                Shape::Image(hash) => Some(hash),
+                _ => None,
+            })
+            .filter_map(|hash| {
+                let data =
+                    Self::load_optional_field::<ImageData>(&*self.storage, &Self::image_key(hash));
+                Some((hash, data?))
+            })
+            .collect()
+    }
+
+    /// Collects the paths from the bottom, with the images which they refer to.
+    fn clip_of(&self, ids: impl IntoIterator<Item = PathId>) -> Clip {
+        let paths = (self.stacking().sort(ids).into_iter())
+            .map(|id| self.path(id).clone())
+            .collect::<Vec<_>>();
+        let images = self.images_of(paths.iter().map(|path| path.get().get()));
+        Clip { paths, images }
+    }
+
+    /// Inserts the clipped paths with new IDs, centered at `anchor`
+    /// (or the center of the screen if [`None`]), and selects them if a selector is used.
+    pub fn paste_clip(&mut self, clip: Clip, anchor: Option<OnScreen<Coordinate<i32>>>) {
+        let rect = match (clip.paths.iter())
+            .map(|path| *path.bounding_rect().get())
+            .reduce(utils::rect_union)
+        {
+            Some(rect) => rect,
+            None => return,
+        };
+        for data in clip.images.values() {
+            self.store_image(data);
+        }
+        let delta = self.anchor_coord(anchor) - (rect.min() + rect.max()) / 2;
+        let paths = (clip.paths.into_iter()).map(|mut path| (PathId::gen(), path.take()));
+        self.insert_copies(paths, delta);
+    }
+
+    /// Inserts the paths made by another application with new IDs, centered at `anchor`
+    /// (or the center of the screen if [`None`]), in the palette colors nearest to theirs.
+    pub fn import_paths(
+        &mut self,
+        paths: impl IntoIterator<Item = Path>,
+        anchor: Option<OnScreen<Coordinate<i32>>>,
+    ) {
+        let palette = self.palette.get();
+        let paths = (paths.into_iter())
+            .filter_map(|mut path| {
+                path.color = palette.nearest(path.color).unwrap_or(path.color);
+                RenderablePath::new(path)
+            })
+            .collect();
+        let clip = Clip {
+            paths,
+            images: FxHashMap::default(),
+        };
+        self.paste_clip(clip, anchor);
+    }
+
+    /// Duplicates the selected paths slightly apart from them, and selects the copies instead.
+    pub fn duplicate_selected_paths(&mut self) {
+        if self.selected_path_ids.get().is_empty() {
+            return;
+        }
+        let ids = self
+            .selected_path_ids
+            .get()
+            .iter()
+            .copied()
+            .collect::<Vec<_>>();
+        self.duplicate_paths(ids, Self::DUPLICATE_OFFSET);
+    }
+
+    /// Inserts copies of the paths shifted by `delta`, and selects them if a selector is used.
+    pub fn duplicate_paths(
+        &mut self,
+        ids: impl IntoIterator<Item = PathId>,
+        delta: Coordinate<i32>,
+    ) {
+        // the copies are stacked as the originals, taking orders in turn
+        let ids = self.stacking().sort(ids);
+        // the copies are grouped as the originals but apart from them
+        let mut copy_groups = FxHashMap::default();
+        let paths = (ids.into_iter())
+            .map(|id| {
+                let copy_id = PathId::gen();
+                self.inherit_layer(copy_id, id);
+                if let Some(&group) = self.path_groups.get().get(&id) {
+                    let copy_group = *copy_groups.entry(group).or_insert_with(GroupId::gen);
+                    self.path_groups.get_mut().insert(copy_id, copy_group);
+                }
+                (copy_id, self.path(id).get().get().clone())
+            })
+            .collect::<Vec<_>>();
+        self.insert_copies(paths, delta);
+    }
+
+    /// Inserts the paths with new IDs shifted by `delta`, and selects them if a selector is used.
+    fn insert_copies(
+        &mut self,
+        paths: impl IntoIterator<Item = (PathId, Path)>,
+        delta: Coordinate<i32>,
+    ) {
+        let paths = (paths.into_iter())
+            .map(|(id, mut path)| {
+                path.coords.translate_inplace(delta.x, delta.y);
+                let path = RenderablePath::new(path).expect("`path` should not be empty");
+                (id, path)
+            })
+            .collect::<Vec<_>>();
+        let ids = paths.iter().map(|&(id, _)| id).collect::<Vec<_>>();
+        self.insert_paths(paths);
+        if self.tool.get().is_selector() {
+            self.unselect_all_paths();
+            self.selected_path_ids.get_mut().extend(ids);
+        }
+    }
+
+    /// Applies the affine transformation to the paths, which is undone exactly.
+    pub fn transform_paths(&mut self, ids: impl IntoIterator<Item = PathId>, transform: Affine) {
+        let coords = (ids.into_iter())
+            .map(|id| {
+                let coords = &self.path(id).get().get().coords;
+                (id, transform.apply_to_line_string(coords))
+            })
+            .collect::<Vec<_>>();
+        self.set_paths_coords(coords, transform);
+    }
+
+    /// Replaces the coordinates of the paths, which have been transformed by `transform`.
+    fn set_paths_coords(
+        &mut self,
+        coords: impl IntoIterator<Item = (PathId, LineString<i32>)>,
+        transform: Affine,
+    ) {
+        let paths = (coords.into_iter())
+            .map(|(id, coords)| {
+                let path = self.paths.get_mut().get_mut(&id).expect("path not found");
+                let old_coords;
+                *path = {
+                    let mut path = path.take();
+                    old_coords = mem::replace(&mut path.coords, coords);
+                    RenderablePath::new(path).expect("`path` should not be empty")
+                };
+                self.tiling.remove_path(id);
+                self.tiling.insert_path(id, path);
+                self.path_store.mark_changed(id);
+                (id, old_coords)
+            })
+            .collect();
+        self.history.push(Command::Transform { paths, transform });
+    }
+
+    /// Removes the paths and inserts `pieces` cut from them, which is undone at once.
+    ///
+    /// Each piece is given with the ID of the path which it is cut from.
+    pub fn cut_paths(
+        &mut self,
+        ids: impl IntoIterator<Item = PathId>,
+        pieces: impl IntoIterator<Item = (PathId, RenderablePath)>,
+    ) {
+        let pieces = (pieces.into_iter())
+            .map(|(from, piece)| {
+                let id = PathId::gen();
+                self.inherit_layer(id, from);
+                if let Some(&order) = self.path_orders.get().get(&from) {
+                    self.path_orders.get_mut().insert(id, order);
+                }
+                if let Some(&group) = self.path_groups.get().get(&from) {
+                    self.path_groups.get_mut().insert(id, group);
+                }
+                (id, piece)
+            })
+            .collect::<Vec<_>>();
+        self.history.start_group();
+        self.remove_paths(ids);
+        self.insert_paths(pieces);
+        self.history
+            .finish_group(|commands| Command::Batch { commands });
+    }
+
+    /// Moves the selected paths in the stacking order, which can be undone.
+    pub fn restack_selected_paths(&mut self, restack: Restack) {
+        if self.selected_path_ids.get().is_empty() {
+            return;
+        }
+        let selected = self.selected_path_ids.get();
+        let mut stack = (self.paths.get().keys())
+            .copied()
+            .sorted_by_key(|&id| self.stack_key(id))
+            .collect::<Vec<_>>();
+        // makes the orders distinct so that swapping the paths changes their orders
+        let mut orders = (stack.iter())
+            .map(|id| self.path_orders.get().get(id).copied().unwrap_or_default())
+            .collect::<Vec<_>>();
+        for i in 1..orders.len() {
+            orders[i] = orders[i].max(orders[i - 1] + 1);
+        }
+        let can_swap = |stack: &[PathId], lower: usize, upper: usize| {
+            selected.contains(&stack[lower]) != selected.contains(&stack[upper])
+                && self.layer_of(stack[lower]).id == self.layer_of(stack[upper]).id
+        };
+        let new_orders = match restack {
+            Restack::Forward => {
+                for i in (1..stack.len()).rev() {
+                    if selected.contains(&stack[i - 1]) && can_swap(&stack, i - 1, i) {
+                        stack.swap(i - 1, i);
+                    }
+                }
+                stack.into_iter().zip(orders).collect::<Vec<_>>()
+            }
+            Restack::Backward => {
+                for i in 1..stack.len() {
+                    if selected.contains(&stack[i]) && can_swap(&stack, i - 1, i) {
+                        stack.swap(i - 1, i);
+                    }
+                }
+                stack.into_iter().zip(orders).collect()
+            }
+            Restack::ToFront => {
+                let top = orders.last().map_or(0, |&max| max + 1);
+                let (selected, others) = (stack.iter().zip(orders))
+                    .partition::<Vec<_>, _>(|(id, _)| selected.contains(id));
+                let selected = (selected.into_iter())
+                    .zip(top..)
+                    .map(|((&id, _), order)| (id, order));
+                others
+                    .into_iter()
+                    .map(|(&id, order)| (id, order))
+                    .chain(selected)
+                    .collect()
+            }
+            Restack::ToBack => {
+                let (selected, others) = (stack.iter().zip(orders.iter().copied()))
+                    .partition::<Vec<_>, _>(|(id, _)| selected.contains(id));
+                let bottom = orders.first().copied().unwrap_or_default() - selected.len() as i64;
+                let selected = (selected.into_iter())
+                    .zip(bottom..)
+                    .map(|((&id, _), order)| (id, order));
+                selected
+                    .chain(others.into_iter().map(|(&id, order)| (id, order)))
+                    .collect()
+            }
+        };
+        let new_orders = (new_orders.into_iter())
+            .filter(|(id, order)| self.path_orders.get().get(id) != Some(order))
+            .collect::<Vec<_>>();
+        if !new_orders.is_empty() {
+            self.set_path_orders(new_orders);
+        }
+    }
+
+    fn set_path_orders(&mut self, orders: impl IntoIterator<Item = (PathId, i64)>) {
+        let orders = (orders.into_iter())
+            .map(|(id, order)| {
+                let old = self.path_orders.get_mut().insert(id, order);
+                (id, old.unwrap_or_default())
+            })
+            .collect();
+        self.history.push(Command::Restack { orders });
+    }
+
+    /// Groups the selected paths into one, which can be undone.
+    pub fn group_selected_paths(&mut self) {
+        if self.selected_path_ids.get().len() < 2 {
+            return;
+        }
+        let group = GroupId::gen();
+        let groups = (self.selected_path_ids.get().iter())
+            .map(|&id| (id, Some(group)))
+            .collect::<Vec<_>>();
+        self.set_path_groups(groups);
+    }
+
+    /// Ungroups the selected paths, which can be undone.
+    pub fn ungroup_selected_paths(&mut self) {
+        let groups = (self.selected_path_ids.get().iter())
+            .filter(|id| self.path_groups.get().contains_key(id))
+            .map(|&id| (id, None))
+            .collect::<Vec<_>>();
+        if !groups.is_empty() {
+            self.set_path_groups(groups);
+        }
+    }
+
+    fn set_path_groups(&mut self, groups: impl IntoIterator<Item = (PathId, Option<GroupId>)>) {
+        let groups = (groups.into_iter())
+            .map(|(id, group)| {
+                let old = match group {
+                    Some(group) => self.path_groups.get_mut().insert(id, group),
+                    None => self.path_groups.get_mut().remove(&id),
+                };
+                (id, old)
+            })
+            .collect();
+        self.history.push(Command::Regroup { groups });
+    }
+
+    /// Locks the selected paths against selecting and erasing, and unselects them.
+    pub fn lock_selected_paths(&mut self) {
+        if self.selected_path_ids.get().is_empty() {
+            return;
+        }
+        let ids = mem::take(self.selected_path_ids.get_mut());
+        self.locked_path_ids.get_mut().extend(ids);
+    }
+
+    /// Unlocks the locked paths in the editable layers, and selects them instead.
+    pub fn unlock_all_paths(&mut self) {
+        let ids = (self.locked_path_ids.get().iter())
+            .copied()
+            .filter(|&id| self.paths.get().contains_key(&id) && self.layer_of(id).is_editable())
+            .collect::<FxHashSet<_>>();
+        if ids.is_empty() {
+            return;
+        }
+        self.locked_path_ids.update(|l| {
+            l.retain(|id| !ids.contains(id));
+            true
+        });
+        *self.selected_path_ids.get_mut() = ids;
+    }
+
+    /// Returns the paths together with the editable paths grouped with any of them.
+    pub fn with_groups(&self, ids: impl IntoIterator<Item = PathId>) -> FxHashSet<PathId> {
+        let mut ids = ids.into_iter().collect::<FxHashSet<_>>();
+        let groups = (ids.iter())
+            .filter_map(|id| self.path_groups.get().get(id).copied())
+            .collect::<FxHashSet<_>>();
+        if !groups.is_empty() {
+            ids.extend(
+                (self.path_groups.get().iter())
+                    .filter(|&(&id, group)| {
+                        groups.contains(group)
+                            && self.paths.get().contains_key(&id)
+                            && self.is_editable(id)
+                    })
+                    .map(|(&id, _)| id),
+            );
+        }
+        ids
+    }
+
+    /// Replaces the paths with the same IDs by the given ones.
+    pub fn replace_paths(&mut self, paths: impl IntoIterator<Item = (PathId, RenderablePath)>) {
+        let paths = (paths.into_iter())
+            .map(|(id, path)| {
+                self.tiling.remove_path(id);
+                self.tiling.insert_path(id, &path);
+                self.path_store.mark_changed(id);
+                let old = self.paths.get_mut().get_mut(&id).expect("path not found");
+                (id, mem::replace(old, path))
+            })
+            .collect();
+        self.history.push(Command::Replace { paths });
+    }
+
     pub fn remove_selected_paths(&mut self) {
         if self.selected_path_ids.get().is_empty() {
             return;
@@ -217,15 +1010,17 @@ impl Model {
         *self.selected_path_ids.get_mut() = ids; // restore capacity
     }
 
+    /// Removes all the paths which can be erased, leaving locked ones and those in locked or
+    /// hidden layers.
     pub fn clear_paths(&mut self) {
-        if self.paths.get().is_empty() {
+        let ids = (self.paths.get().keys())
+            .copied()
+            .filter(|&id| self.is_editable(id))
+            .collect::<Vec<_>>();
+        if ids.is_empty() {
             return;
         }
-        let paths = self.paths.get_mut().drain().collect();
-        self.tiling.clear();
-        self.selected_path_ids.get_mut().clear();
-        self.hidden_path_ids.get_mut().clear();
-        self.history.push(Command::Remove { paths });
+        self.remove_paths(ids);
     }
 
     pub fn selected_paths(&self) -> impl Iterator<Item = (PathId, &RenderablePath)> {
@@ -235,15 +1030,19 @@ impl Model {
             .map(move |&id| (id, self.paths.get().get(&id).expect("path not found")))
     }
 
-    /// Select paths which intersect `rect`, contained by `whole_rect`.
+    /// Select paths which intersect `rect`, contained by `whole_rect` including their widths.
     pub fn select_paths_with(&mut self, whole_rect: Rect<i32>, rect: Rect<i32>) {
-        let ids = (self.tiling.bounding_tile_items(rect))
+        let rect = utils::expand_rect(rect, Width::MAX.half_ceil());
+        let ids = (self.bounding_tile_items(rect))
             .filter(|&(id, _)| {
                 let path = self.paths.get().get(&id).expect("path not found");
-                let coords = &path.get().get().coords;
-                whole_rect.contains(&coords.bounding_rect().expect("empty path"))
+                let bounding_rect = path.outline().bounding_rect().expect("empty path");
+                let margin = path.get().get().width.half_ceil();
+                whole_rect.contains(&utils::expand_rect(bounding_rect, margin))
             })
-            .map(|(id, _)| id);
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        let ids = self.with_groups(ids);
         self.selected_path_ids.update(|s| {
             let prev_len = s.len();
             s.extend(ids);
@@ -251,16 +1050,159 @@ impl Model {
         });
     }
 
-    /// Unselect paths which intersect `rect`.
+    /// Unselect paths which intersect `rect`, including their widths.
     pub fn unselect_paths_with(&mut self, rect: Rect<i32>) {
-        let ids = (self.tiling.bounding_tile_items(rect))
-            .filter(|(_, lines)| lines.iter().any(|line| line.intersects(&rect)))
-            .map(|(id, _)| id);
-        for id in ids {
+        let ids = (self
+            .tiling
+            .bounding_tile_items(utils::expand_rect(rect, Width::MAX.half_ceil())))
+        .filter(|&(id, lines)| {
+            let path = self.paths.get().get(&id).expect("path not found");
+            let rect = utils::expand_rect(rect, path.get().get().width.half_ceil());
+            lines.iter().any(|line| line.intersects(&rect))
+                || matches!(path.area(), Some(area) if area.intersects(&rect))
+        })
+        .map(|(id, _)| id)
+        .collect::<Vec<_>>();
+        for id in self.with_groups(ids) {
             self.selected_path_ids.update(|s| s.remove(&id));
         }
     }
 
+    /// Selects the topmost path at `coord`, including its width, with the paths grouped with it.
+    pub fn select_path_at(&mut self, coord: Coordinate<i32>) {
+        let point = Rect::new(coord, coord);
+        let id = (self.bounding_tile_items(utils::expand_rect(point, Width::MAX.half_ceil())))
+            .filter(|&(id, lines)| {
+                let path = self.paths.get().get(&id).expect("path not found");
+                let rect = utils::expand_rect(point, path.get().get().width.half_ceil());
+                lines.iter().any(|line| line.intersects(&rect))
+                    || matches!(path.area(), Some(area) if area.intersects(&rect))
+            })
+            .map(|(id, _)| id)
+            .max_by_key(|&id| self.stack_key(id));
+        if let Some(id) = id {
+            let ids = self.with_groups([id]);
+            self.selected_path_ids.update(|s| {
+                let prev_len = s.len();
+                s.extend(ids);
+                s.len() != prev_len
+            });
+        }
+    }
+
+    /// Select paths inside `lasso` as specified by `mode`.
+    pub fn select_paths_in(&mut self, lasso: Polygon<i32>, mode: LassoMode) {
+        let lasso_f = lasso.map_scalars(f64::from);
+        let ids = (self.bounding_tile_items(lasso))
+            .map(|(id, _)| id)
+            .filter(|id| {
+                let path = self.paths.get().get(id).expect("path not found");
+                let outline = path.outline().map_scalars(f64::from);
+                match (mode, path.area()) {
+                    (LassoMode::Whole, _) => lasso_f.contains(&outline),
+                    (LassoMode::Partial, None) => lasso_f.intersects(&outline),
+                    (LassoMode::Partial, Some(area)) => {
+                        lasso_f.intersects(&area.map_scalars(f64::from))
+                    }
+                }
+            })
+            .collect::<Vec<_>>();
+        let ids = self.with_groups(ids);
+        self.selected_path_ids.update(|s| {
+            let prev_len = s.len();
+            s.extend(ids);
+            s.len() != prev_len
+        });
+    }
+
+    pub fn select_all_paths(&mut self) {
+        let ids = (self.paths.get().keys())
+            .copied()
+            .filter(|&id| self.is_editable(id))
+            .collect::<Vec<_>>();
+        self.selected_path_ids.update(|s| {
+            let prev_len = s.len();
+            s.extend(ids);
+            s.len() != prev_len
+        });
+    }
+
+    /// Selects the paths of the same colors as the selected ones instead,
+    /// or of the pen color if nothing selected.
+    pub fn select_paths_of_same_color(&mut self) {
+        let mut colors = (self.selected_paths())
+            .map(|(_, path)| path.get().get().color)
+            .collect::<FxHashSet<_>>();
+        if colors.is_empty() {
+            colors.insert(*self.pen_color.get());
+        }
+        let ids = (self.paths.get().iter())
+            .filter(|&(&id, path)| colors.contains(&path.get().get().color) && self.is_editable(id))
+            .map(|(&id, _)| id)
+            .collect::<Vec<_>>();
+        *self.selected_path_ids.get_mut() = self.with_groups(ids);
+    }
+
+    pub fn invert_selection(&mut self) {
+        if self.paths.get().is_empty() {
+            return;
+        }
+        let selected = self.selected_path_ids.get();
+        let ids = (self.paths.get().keys())
+            .copied()
+            .filter(|&id| !selected.contains(&id) && self.is_editable(id))
+            .collect();
+        *self.selected_path_ids.get_mut() = ids;
+    }
+
+    /// Selects the paths visible on the screen instead.
+    pub fn select_paths_on_screen(&mut self) {
+        let rect = self.board_rect();
+        let ids = (self.bounding_tile_items(rect))
+            .filter(|&(id, lines)| {
+                let path = self.paths.get().get(&id).expect("path not found");
+                lines.iter().any(|line| line.intersects(&rect))
+                    || matches!(path.area(), Some(area) if area.intersects(&rect))
+            })
+            .map(|(id, _)| id)
+            .collect::<Vec<_>>();
+        *self.selected_path_ids.get_mut() = self.with_groups(ids);
+    }
+
+    /// Returns the bounding rectangle of the selected paths which are not hidden.
+    pub fn selection_rect(&self) -> Option<Rect<i32>> {
+        (self.selected_paths())
+            .filter(|(id, _)| !self.hidden_path_ids.get().contains(id))
+            .map(|(_, path)| *path.bounding_rect().get())
+            .reduce(utils::rect_union)
+    }
+
+    /// Returns the handles around the selected paths with their positions on the board.
+    fn handles(&self) -> Vec<(Handle, Coordinate<f64>)> {
+        let rect = match self.selection_rect() {
+            Some(rect) => rect,
+            None => return vec![],
+        };
+        let corners = utils::rect_corners(rect).map(|c| utils::coord_map_scalars(c, f64::from));
+        let rotate = Coordinate {
+            x: (corners[0].x + corners[1].x) / 2.,
+            y: corners[0].y - Self::ROTATE_HANDLE_DISTANCE / self.scale.get().get(),
+        };
+        (corners.into_iter().enumerate())
+            .map(|(i, c)| (Handle::Scale(i), c))
+            .chain(iter::once((Handle::Rotate, rotate)))
+            .collect()
+    }
+
+    /// Returns the handle at `coord` on the board, if any.
+    pub fn handle_at(&self, coord: Coordinate<i32>) -> Option<Handle> {
+        let coord = utils::coord_map_scalars(coord, f64::from);
+        let radius = Self::HANDLE_RADIUS / self.scale.get().get();
+        (self.handles().into_iter())
+            .find(|&(_, c)| Point::from(c).euclidean_distance(&Point::from(coord)) <= radius)
+            .map(|(handle, _)| handle)
+    }
+
     pub fn unselect_all_paths(&mut self) {
         if self.selected_path_ids.get().is_empty() {
             return;
@@ -289,6 +1231,27 @@ impl Model {
             Command::Remove { paths } => {
                 self.insert_paths(paths);
             }
+            Command::Replace { paths } => {
+                self.replace_paths(paths);
+            }
+            Command::Transform { paths, transform } => {
+                let inverse = transform.inverse().expect("singular transformation");
+                self.set_paths_coords(paths, inverse);
+            }
+            Command::Restack { orders } => {
+                self.set_path_orders(orders);
+            }
+            Command::Regroup { groups } => {
+                self.set_path_groups(groups);
+            }
+            Command::Batch { commands } => {
+                self.history.start_group();
+                for com in commands.into_iter().rev() {
+                    self.rollback(com);
+                }
+                self.history
+                    .finish_group(|commands| Command::Batch { commands });
+            }
         }
     }
 
@@ -305,12 +1268,17 @@ impl Model {
     }
 
     pub fn coord_at(&self, coord: OnScreen<Coordinate<i32>>) -> Coordinate<i32> {
-        coord.0 - *self.offset.get()
+        let scale = self.scale.get().get();
+        utils::coord_map_scalars(coord.0 - *self.offset.get(), |s| {
+            (f64::from(s) / scale).floor() as _
+        })
     }
 
-    pub fn delta_of(&self, delta: OnScreen<Coordinate<i32>>) -> Coordinate<i32> {
-        // consider scaling in the future
-        delta.0
+    /// Returns the position on the screen of the coordinate on the board.
+    fn coord_on_screen(&self, coord: Coordinate<i32>) -> OnScreen<Coordinate<f64>> {
+        let scale = self.scale.get().get();
+        let offset = utils::coord_map_scalars(*self.offset.get(), f64::from);
+        OnScreen(offset + utils::coord_map_scalars(coord, |s| f64::from(s) * scale))
     }
 
     fn board_rect(&self) -> Rect<i32> {
@@ -322,9 +1290,35 @@ impl Model {
         Rect::new(self.coord_at(origin), self.coord_at(diagonal))
     }
 
-    pub fn scroll(&mut self, delta: Coordinate<i32>) {
-        *self.offset.get_mut() = *self.offset.get() + delta;
-        self.view.translate(delta);
+    pub fn scroll(&mut self, delta: OnScreen<Coordinate<i32>>) {
+        *self.offset.get_mut() = *self.offset.get() + delta.0;
+        self.view
+            .transform(OnScreen(*self.offset.get()), *self.scale.get());
+        self.place_text_editor();
+    }
+
+    /// Multiplies the scale by `factor`, keeping the point at `anchor` (or the center of the
+    /// screen if [`None`]) fixed on the screen.
+    pub fn zoom(&mut self, factor: f64, anchor: Option<OnScreen<Coordinate<i32>>>) {
+        let anchor = anchor.unwrap_or_else(|| {
+            self.view
+                .size()
+                .map(|size| utils::coord_map_scalars(size, |s| s as i32 / 2))
+        });
+        let old_scale = self.scale.get().get();
+        let new_scale = Scale::new(old_scale * factor);
+        if new_scale == *self.scale.get() {
+            return;
+        }
+        // the anchor should point to the same coordinate on the board before and after zooming
+        let rel_scale = new_scale.get() / old_scale;
+        let rel_anchor = utils::coord_map_scalars(anchor.0 - *self.offset.get(), |s| {
+            (f64::from(s) * rel_scale).round() as i32
+        });
+        *self.offset.get_mut() = anchor.0 - rel_anchor;
+        *self.scale.get_mut() = new_scale;
+        self.view.transform(OnScreen(*self.offset.get()), new_scale);
+        self.place_text_editor();
     }
 
     pub fn tool(&self) -> Tool {
@@ -333,7 +1327,7 @@ impl Model {
 
     pub fn set_tool(&mut self, tool: Tool) {
         *self.tool.get_mut() = tool;
-        if tool != Tool::Selector {
+        if !tool.is_selector() {
             self.unselect_all_paths();
         }
         self.view.select_tool(tool);
@@ -348,28 +1342,434 @@ impl Model {
         self.view.select_pen_color(color);
     }
 
+    /// Adds the current pen color to the palette unless it already contains the color.
+    pub fn add_pen_color_to_palette(&mut self) {
+        let color = *self.pen_color.get();
+        if self.palette.get().0.contains(&color) {
+            return;
+        }
+        self.palette.get_mut().0.push(color);
+        self.view.render_palette(self.palette.get());
+        self.view.select_pen_color(color);
+    }
+
+    pub fn remove_color_from_palette(&mut self, color: Color) {
+        if !self.palette.get().0.contains(&color) {
+            return;
+        }
+        self.palette.get_mut().0.retain(|&c| c != color);
+        self.view.render_palette(self.palette.get());
+        self.view.select_pen_color(*self.pen_color.get());
+    }
+
+    pub fn pen_width(&self) -> Width {
+        *self.pen_width.get()
+    }
+
+    pub fn set_pen_width(&mut self, width: Width) {
+        *self.pen_width.get_mut() = width;
+        self.view.select_pen_width(width);
+    }
+
+    pub fn font_size(&self) -> FontSize {
+        *self.font_size.get()
+    }
+
+    pub fn set_font_size(&mut self, size: FontSize) {
+        *self.font_size.get_mut() = size;
+        self.view.select_font_size(size);
+    }
+
+    pub fn eraser_radius(&self) -> EraserRadius {
+        *self.eraser_radius.get()
+    }
+
+    pub fn set_eraser_radius(&mut self, radius: EraserRadius) {
+        *self.eraser_radius.get_mut() = radius;
+        self.view.select_eraser_radius(radius);
+    }
+
+    pub fn lasso_mode(&self) -> LassoMode {
+        *self.lasso_mode.get()
+    }
+
+    pub fn set_lasso_mode(&mut self, mode: LassoMode) {
+        *self.lasso_mode.get_mut() = mode;
+        self.view.select_lasso_mode(mode);
+    }
+
+    pub fn boards(&self) -> &Boards {
+        self.boards.get()
+    }
+
+    /// Loads the active board from the storage again, discarding the unsaved changes.
+    fn reload(&mut self) {
+        Self::save_field(&*self.storage, "boards", &self.boards);
+        self.view.layers[Layer::Temp].clear();
+        let (storage, view) = (Rc::clone(&self.storage), self.view.clone());
+        *self = Self::load(storage, view);
+    }
+
+    /// Switches to the board, discarding the history of the current one.
+    pub fn switch_board(&mut self, id: BoardId) {
+        if id == self.boards.get().active || self.boards.get().get(id).is_none() {
+            return;
+        }
+        self.finish_text_editing();
+        self.save();
+        self.boards.get_mut().active = id;
+        self.reload();
+    }
+
+    /// Adds a new empty board and switches to it.
+    pub fn add_board(&mut self) {
+        let name = Boards::default_name(self.boards.get().list.len() + 1);
+        let board = Board::new(name);
+        let id = board.id;
+        self.boards.get_mut().list.push(board);
+        self.switch_board(id);
+    }
+
+    pub fn rename_board(&mut self, id: BoardId, name: String) {
+        if let Some(board) = self.boards.get_mut().get_mut(id) {
+            board.name = name;
+        }
+        self.view.render_boards(self.boards.get());
+    }
+
+    /// Returns the keys in the storage under which the fields of the board are stored.
+    fn board_storage_keys(&self, id: BoardId) -> Vec<String> {
+        let prefix = format!("papirs:{}", Self::board_key(id, ""));
+        (self.storage.keys().into_iter())
+            .filter(|key| key.starts_with(&prefix))
+            .collect()
+    }
+
+    /// Adds a copy of the board next to it and switches to the copy.
+    pub fn duplicate_board(&mut self, id: BoardId) {
+        let (pos, name) = match self.boards.get().position(id) {
+            Some(pos) => (pos, format!("{} (copy)", self.boards.get().list[pos].name)),
+            None => return,
+        };
+        self.finish_text_editing();
+        self.save(); // the copy is made from the storage
+        let copy = Board::new(name);
+        let prefix = format!("papirs:{}", Self::board_key(id, ""));
+        let copy_prefix = format!("papirs:{}", Self::board_key(copy.id, ""));
+        for key in self.board_storage_keys(id) {
+            let copy_key = key.replacen(&prefix, &copy_prefix, 1);
+            if let Err(err) = self.storage.copy(&key, &copy_key) {
+                log::error!("failed to duplicate the board: {}", err);
+                for key in self.board_storage_keys(copy.id) {
+                    self.storage.remove(&key);
+                }
+                return;
+            }
+        }
+        let copy_id = copy.id;
+        self.boards.get_mut().list.insert(pos + 1, copy);
+        self.switch_board(copy_id);
+    }
+
+    /// Removes the board with its paths from the storage, unless it is the last one.
+    pub fn remove_board(&mut self, id: BoardId) {
+        let boards = self.boards.get();
+        let pos = match boards.position(id) {
+            Some(pos) if boards.list.len() > 1 => pos,
+            _ => return,
+        };
+        self.finish_text_editing();
+        self.save();
+        for key in self.board_storage_keys(id) {
+            self.storage.remove(&key);
+        }
+        self.boards.get_mut().list.remove(pos);
+        if id == self.boards.get().active {
+            // the stored images only used by the board are removed on the next loading
+            let next = self.boards.get().list[pos.saturating_sub(1)].id;
+            self.boards.get_mut().active = next;
+            self.reload();
+        } else {
+            self.view.render_boards(self.boards.get());
+        }
+    }
+
+    /// Puts the new path into the layer of the path `from`.
+    fn inherit_layer(&mut self, id: PathId, from: PathId) {
+        if let Some(&layer) = self.path_layers.get().get(&from) {
+            self.path_layers.get_mut().insert(id, layer);
+        }
+    }
+
+    pub fn doc_layers(&self) -> &DocLayers {
+        self.doc_layers.get()
+    }
+
+    /// Updates the layers by `f`, unselecting the paths which are no longer editable.
+    fn update_doc_layers(&mut self, f: impl FnOnce(&mut DocLayers)) {
+        f(self.doc_layers.get_mut());
+        self.unselect_uneditable_paths();
+        self.view.render_doc_layers(self.doc_layers.get());
+    }
+
+    fn unselect_uneditable_paths(&mut self) {
+        let ids = (self.selected_path_ids.get().iter())
+            .copied()
+            .filter(|&id| !self.is_editable(id))
+            .collect::<Vec<_>>();
+        for id in ids {
+            self.selected_path_ids.update(|s| s.remove(&id));
+        }
+    }
+
+    /// Adds a new layer above the active one and activates it.
+    pub fn add_doc_layer(&mut self) {
+        self.update_doc_layers(|layers| {
+            let layer = DocLayer::new(DocLayers::default_name(layers.list.len() + 1));
+            let pos = layers
+                .position(layers.active)
+                .map_or(layers.list.len(), |i| i + 1);
+            layers.active = layer.id;
+            layers.list.insert(pos, layer);
+        });
+    }
+
+    /// Removes the layer along with its paths, unless it is the last one.
+    ///
+    /// The removal of the paths can be undone, which brings them into the bottom layer.
+    pub fn remove_doc_layer(&mut self, id: DocLayerId) {
+        let pos = match self.doc_layers.get().position(id) {
+            Some(pos) if self.doc_layers.get().list.len() > 1 => pos,
+            _ => return,
+        };
+        let ids = (self.paths.get().keys())
+            .copied()
+            .filter(|&path_id| self.layer_of(path_id).id == id)
+            .collect::<Vec<_>>();
+        for path_id in &ids {
+            self.selected_path_ids.update(|s| s.remove(path_id));
+        }
+        if !ids.is_empty() {
+            self.remove_paths(ids);
+        }
+        self.update_doc_layers(|layers| {
+            layers.list.remove(pos);
+            if layers.active == id {
+                layers.active = layers.list[pos.saturating_sub(1)].id;
+            }
+        });
+    }
+
+    pub fn set_active_doc_layer(&mut self, id: DocLayerId) {
+        self.update_doc_layers(|layers| {
+            if layers.get(id).is_some() {
+                layers.active = id;
+            }
+        });
+    }
+
+    pub fn rename_doc_layer(&mut self, id: DocLayerId, name: String) {
+        self.update_doc_layers(|layers| {
+            if let Some(layer) = layers.get_mut(id) {
+                layer.name = name;
+            }
+        });
+    }
+
+    pub fn set_doc_layer_visible(&mut self, id: DocLayerId, visible: bool) {
+        self.update_doc_layers(|layers| {
+            if let Some(layer) = layers.get_mut(id) {
+                layer.visible = visible;
+            }
+        });
+    }
+
+    pub fn set_doc_layer_locked(&mut self, id: DocLayerId, locked: bool) {
+        self.update_doc_layers(|layers| {
+            if let Some(layer) = layers.get_mut(id) {
+                layer.locked = locked;
+            }
+        });
+    }
+
+    /// Moves the layer up (toward the front) if `up`, or down otherwise.
+    pub fn move_doc_layer(&mut self, id: DocLayerId, up: bool) {
+        self.update_doc_layers(|layers| {
+            let pos = match layers.position(id) {
+                Some(pos) => pos,
+                None => return,
+            };
+            let new_pos = if up {
+                pos + 1
+            } else {
+                match pos.checked_sub(1) {
+                    Some(new_pos) => new_pos,
+                    None => return,
+                }
+            };
+            if new_pos < layers.list.len() {
+                layers.list.swap(pos, new_pos);
+            }
+        });
+    }
+
+    /// Moves the selected paths into the layer.
+    pub fn move_selected_paths_to_doc_layer(&mut self, id: DocLayerId) {
+        if self.selected_path_ids.get().is_empty() || self.doc_layers.get().get(id).is_none() {
+            return;
+        }
+        let ids = self
+            .selected_path_ids
+            .get()
+            .iter()
+            .copied()
+            .collect::<Vec<_>>();
+        self.path_layers
+            .get_mut()
+            .extend(ids.into_iter().map(|path_id| (path_id, id)));
+        self.unselect_uneditable_paths();
+    }
+
+    /// Returns the text at `coord` on the board, if any.
+    fn text_at(&self, coord: Coordinate<i32>) -> Option<PathId> {
+        (self.bounding_tile_items(Rect::new(coord, coord)))
+            .map(|(id, _)| id)
+            .find(|id| {
+                let path = self.paths.get().get(id).expect("path not found");
+                matches!(path.get().get().shape, Shape::Text(_))
+                    && matches!(path.area(), Some(area) if area.intersects(&coord))
+            })
+    }
+
+    pub fn is_editing_text(&self) -> bool {
+        self.editing_text.is_some()
+    }
+
+    /// Opens the text editor at `coord` on the board,
+    /// which edits the text there if any or creates a new one otherwise.
+    pub fn start_text_editing(&mut self, coord: Coordinate<i32>) {
+        self.finish_text_editing();
+        let (editing, content) = match self.text_at(coord) {
+            Some(id) => {
+                let path = self.path(id).get().get();
+                let text = match &path.shape {
+                    Shape::Text(text) => text,
+                    _ => unreachable!("not a text"),
+                };
+                let editing = TextEditing {
+                    id: Some(id),
+                    coord: path.coords.0[0],
+                    color: path.color,
+                    font_size: text.font_size,
+                };
+                let content = text.content.clone();
+                self.hide_path(id);
+                (editing, content)
+            }
+            None => {
+                let editing = TextEditing {
+                    id: None,
+                    coord,
+                    color: self.pen_color(),
+                    font_size: self.font_size(),
+                };
+                (editing, String::new())
+            }
+        };
+        self.view.open_text_editor(&content, editing.color);
+        self.editing_text = Some(editing);
+        self.place_text_editor();
+    }
+
+    /// Closes the text editor if open, and inserts, replaces or removes the edited text.
+    pub fn finish_text_editing(&mut self) {
+        let editing = match self.editing_text.take() {
+            Some(editing) => editing,
+            None => return,
+        };
+        let content = self.view.close_text_editor();
+        // the text may have been removed during editing
+        let id = (editing.id).filter(|&id| self.contains_path(id));
+        if let Some(id) = id {
+            self.unhide_path(id);
+            if matches!(&self.path(id).get().get().shape, Shape::Text(t) if t.content == content) {
+                return;
+            }
+        }
+        if content.trim().is_empty() {
+            if let Some(id) = id {
+                self.remove_paths(iter::once(id));
+            }
+            return;
+        }
+        let text = Text {
+            content,
+            font_size: editing.font_size,
+        };
+        let size = self.view.measure_text(&text);
+        let end = editing.coord + utils::coord_map_scalars(size, |s| s.ceil() as _);
+        let shape = Shape::Text(text);
+        let path = Path {
+            color: editing.color,
+            width: Width::MIN,
+            coords: shape.coords_between(editing.coord, end),
+            shape,
+        };
+        let path = RenderablePath::new(path).expect("`path` should not be empty");
+        match id {
+            Some(id) => self.replace_paths(iter::once((id, path))),
+            None => self.insert_paths(iter::once((PathId::gen(), path))),
+        }
+    }
+
+    /// Moves the text editor to the position of the edited text on the screen.
+    fn place_text_editor(&self) {
+        if let Some(editing) = &self.editing_text {
+            let font_size = f64::from(editing.font_size.get()) * self.scale.get().get();
+            self.view
+                .place_text_editor(self.coord_on_screen(editing.coord), font_size);
+        }
+    }
+
     fn rerender_main_layer(&self) {
         self.view.layers[Layer::Main].clear();
         let ids = self
             .tiling
             .bounding_tile_items(self.board_rect())
             .map(|(id, _)| id)
-            .filter(|id| !self.hidden_path_ids.get().contains(id))
+            .filter(|&id| !self.hidden_path_ids.get().contains(&id) && self.layer_of(id).visible)
             .collect::<FxHashSet<_>>(); // remove duplicates to prevent double rendering
-        for id in ids {
-            let path = self.paths.get().get(&id).expect("path not found");
+        let paths = (ids.into_iter())
+            .sorted_by_key(|&id| self.stack_key(id))
+            .map(|id| self.paths.get().get(&id).expect("path not found"));
+        for path in paths {
             self.view.layers[Layer::Main].render_path(path);
         }
     }
 
     fn rerender_sub_layer(&self) {
         self.view.layers[Layer::Sub].clear();
+        let locked_ids = (self.tiling.bounding_tile_items(self.board_rect()))
+            .map(|(id, _)| id)
+            .filter(|&id| {
+                self.locked_path_ids.get().contains(&id)
+                    && !self.hidden_path_ids.get().contains(&id)
+                    && self.layer_of(id).visible
+            })
+            .collect::<FxHashSet<_>>();
+        for id in locked_ids {
+            self.view.layers[Layer::Sub].render_lock_indicator_of(self.path(id));
+        }
         for (_, path) in self
             .selected_paths()
             .filter(|(id, _)| !self.hidden_path_ids.get().contains(id))
         {
             self.view.layers[Layer::Sub].render_bounding_rect_of(path);
         }
+        let radius = Self::HANDLE_RADIUS / self.scale.get().get();
+        for (_, coord) in self.handles() {
+            self.view.layers[Layer::Sub].render_handle(coord, radius);
+        }
     }
 
     pub fn force_rerender(&self) {
@@ -378,14 +1778,23 @@ impl Model {
     }
 
     fn rerender(&mut self) {
-        if self.paths.is_updated() || self.offset.is_updated() || self.hidden_path_ids.is_updated()
+        if self.paths.is_updated()
+            || self.offset.is_updated()
+            || self.scale.is_updated()
+            || self.hidden_path_ids.is_updated()
+            || self.doc_layers.is_updated()
+            || self.path_layers.is_updated()
+            || self.path_orders.is_updated()
         {
             self.rerender_main_layer();
         }
         if self.paths.is_updated()
             || self.offset.is_updated()
+            || self.scale.is_updated()
             || self.selected_path_ids.is_updated()
             || self.hidden_path_ids.is_updated()
+            || self.locked_path_ids.is_updated()
+            || self.doc_layers.is_updated()
         {
             self.rerender_sub_layer();
         }
@@ -396,10 +1805,22 @@ impl Model {
         self.rerender();
         self.save();
 
+        self.boards.resolve();
         self.paths.resolve();
         self.offset.resolve();
+        self.scale.resolve();
         self.tool.resolve();
         self.pen_color.resolve();
+        self.pen_width.resolve();
+        self.palette.resolve();
+        self.font_size.resolve();
+        self.eraser_radius.resolve();
+        self.lasso_mode.resolve();
+        self.doc_layers.resolve();
+        self.path_layers.resolve();
+        self.path_orders.resolve();
+        self.path_groups.resolve();
+        self.locked_path_ids.resolve();
         self.selected_path_ids.resolve();
         self.hidden_path_ids.resolve();
     }
@@ -427,3 +1848,44 @@ impl Drop for DeferCommit<'_> {
         self.0.commit();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn stacks_copies_as_originals() {
+        let doc_layers = DocLayers::default();
+        let mut path_layers = FxHashMap::default();
+        let mut path_orders = FxHashMap::default();
+        let ids = (0..16).map(|_| PathId::gen()).collect::<Vec<_>>();
+        for (&id, order) in ids.iter().zip((0..16).rev()) {
+            path_layers.insert(id, doc_layers.active);
+            path_orders.insert(id, order);
+        }
+        let stacking = Stacking {
+            doc_layers: &doc_layers,
+            path_layers: &path_layers,
+            path_orders: &path_orders,
+        };
+        // duplicated as `Model::duplicate_paths` from a selection
+        let originals = stacking.sort(ids.iter().copied().collect::<FxHashSet<_>>());
+        assert_eq!(originals, ids.iter().rev().copied().collect::<Vec<_>>());
+        let mut free_orders = FreeOrders::new(&path_orders);
+        let copies = (originals.iter())
+            .map(|_| (PathId::gen(), free_orders.take(false)))
+            .collect::<Vec<_>>();
+        for &(id, order) in &copies {
+            path_layers.insert(id, doc_layers.active);
+            path_orders.insert(id, order);
+        }
+        let stacking = Stacking {
+            doc_layers: &doc_layers,
+            path_layers: &path_layers,
+            path_orders: &path_orders,
+        };
+        let copies = copies.iter().map(|&(id, _)| id).collect::<Vec<_>>();
+        let all = stacking.sort(path_orders.keys().copied());
+        assert_eq!(all, [originals, copies].concat());
+    }
+}
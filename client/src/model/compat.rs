@@ -1,30 +1,533 @@
 //! Old format data.
 
-use super::Recorder;
+use super::{path_store::PathStore, Model, Recorder};
 use crate::{
-    common::{Color, PathId, RenderablePath, Tool},
-    web,
+    common::{BoardId, Boards, Color, Path, PathId, Shape, Tool, Width},
+    storage::Storage,
 };
-use geo::Coordinate;
+use geo::{Coordinate, LineString};
 use rustc_hash::FxHashMap;
 use serde::Deserialize;
 
+/// The version of the current format, stored as `papirs:version`.
+///
+/// Data without a version is in the format of version 0 or 1.
+pub(super) const VERSION: u32 = 6;
+
+/// The fields of a board in version 4, which were stored under the same keys as the others.
+const BOARD_FIELDS_V4: [&str; 8] = [
+    "paths",
+    "offset",
+    "scale",
+    "doc_layers",
+    "path_layers",
+    "path_orders",
+    "path_groups",
+    "locked_path_ids",
+];
+
+/// Moves the fields of the board into the keys of `board` if the storage is in version 4.
+pub(super) fn move_into_board(storage: &dyn Storage, board: BoardId) {
+    if Model::load_optional_field::<u32>(storage, "version") != Some(4) {
+        return;
+    }
+    for field in BOARD_FIELDS_V4 {
+        let (from, to) = (format!("papirs:{}", field), Model::board_key(board, field));
+        if let Err(err) = storage.copy(&from, &format!("papirs:{}", to)) {
+            log::error!("failed to move `{}`: {}", field, err);
+            return;
+        }
+        storage.remove(&from);
+    }
+    Model::save_field(storage, "version", &5_u32);
+}
+
+/// Splits the paths of the boards into the keys of each path if the storage is in version 5.
+pub(super) fn split_paths(storage: &dyn Storage, boards: &Boards) {
+    if Model::load_optional_field::<u32>(storage, "version") != Some(5) {
+        return;
+    }
+    let mut is_split = true;
+    for board in &boards.list {
+        let key = Model::board_key(board.id, "paths");
+        // the boards already split are skipped when retried
+        if !Model::contains_field(storage, &key) {
+            continue;
+        }
+        // the paths are kept unless split, to be retried on the next loading
+        let paths: Option<FxHashMap<PathId, Path>> = Model::load_optional_field(storage, &key);
+        let is_written = match paths {
+            Some(mut paths) => {
+                // the paths saved after a failure are kept
+                paths.extend(PathStore::read_paths::<Path>(storage, board.id));
+                PathStore::write(storage, board.id, &paths, paths.keys().copied())
+            }
+            None => false,
+        };
+        if !is_written {
+            log::error!("failed to split the paths of board `{}`", board.id);
+            is_split = false;
+            continue;
+        }
+        storage.remove(&format!("papirs:{}", key));
+    }
+    if is_split {
+        Model::save_field(storage, "version", &VERSION);
+    }
+}
+
+/// A color in version 2 or earlier, which was one of the six fixed colors.
+#[derive(Clone, Copy, Debug, Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+enum ColorV2 {
+    Black,
+    Red,
+    Orange,
+    Green,
+    Blue,
+    SkyBlue,
+}
+
+impl Default for ColorV2 {
+    fn default() -> Self {
+        Self::Black
+    }
+}
+
+impl From<ColorV2> for Color {
+    fn from(color: ColorV2) -> Self {
+        match color {
+            ColorV2::Black => Self::BLACK,
+            ColorV2::Red => Self::RED,
+            ColorV2::Orange => Self::ORANGE,
+            ColorV2::Green => Self::GREEN,
+            ColorV2::Blue => Self::BLUE,
+            ColorV2::SkyBlue => Self::SKY_BLUE,
+        }
+    }
+}
+
+/// A path in version 1 or earlier, which has no width.
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct PathV1 {
+    color: ColorV2,
+    coords: LineString<i32>,
+}
+
+impl From<PathV1> for PathV2 {
+    fn from(path: PathV1) -> Self {
+        Self {
+            color: path.color,
+            width: Width::default(),
+            coords: path.coords,
+        }
+    }
+}
+
+impl From<PathV1> for Path {
+    fn from(path: PathV1) -> Self {
+        PathV2::from(path).into()
+    }
+}
+
+/// A path in version 2.
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct PathV2 {
+    color: ColorV2,
+    width: Width,
+    coords: LineString<i32>,
+}
+
+impl From<PathV2> for PathV3 {
+    fn from(path: PathV2) -> Self {
+        Self {
+            color: path.color.into(),
+            width: path.width,
+            coords: path.coords,
+        }
+    }
+}
+
+impl From<PathV2> for Path {
+    fn from(path: PathV2) -> Self {
+        PathV3::from(path).into()
+    }
+}
+
+/// A path in version 3, which is always a freehand stroke.
 #[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct PathV3 {
+    color: Color,
+    width: Width,
+    coords: LineString<i32>,
+}
+
+impl From<PathV3> for Path {
+    fn from(path: PathV3) -> Self {
+        Self {
+            color: path.color,
+            width: path.width,
+            shape: Shape::Freehand,
+            coords: path.coords,
+        }
+    }
+}
+
+/// Data in version 0, which was stored under the single key `papirs`.
+#[derive(Debug, Deserialize)]
+#[cfg_attr(test, derive(serde::Serialize))]
+struct DataV0 {
+    paths: FxHashMap<PathId, PathV1>,
+    offset: Coordinate<i32>,
+    tool: Tool,
+    pen_color: ColorV2,
+}
+
+/// Old format data, converted into the current format.
+#[derive(Debug)]
 pub(super) struct Data {
-    pub paths: Recorder<FxHashMap<PathId, RenderablePath>>,
+    pub paths: FxHashMap<PathId, Path>,
     pub offset: Recorder<Coordinate<i32>>,
     pub tool: Recorder<Tool>,
     pub pen_color: Recorder<Color>,
 }
 
 impl Data {
-    pub fn load_and_remove(storage: &web::Storage) -> Option<Self> {
+    /// Loads data from the storage if it is in an old format, removing keys no longer used.
+    ///
+    /// The caller is responsible for saving the returned data in the current format.
+    pub fn load_and_remove(storage: &dyn Storage) -> Option<Self> {
+        match Model::load_optional_field::<u32>(storage, "version") {
+            Some(5 | VERSION) => None,
+            Some(2) => Some(Self::load_split::<PathV2, ColorV2>(storage)),
+            Some(3) => Some(Self::load_split::<PathV3, Color>(storage)),
+            Some(version) => {
+                log::error!("unknown format version: {}", version);
+                None
+            }
+            None => Some(
+                Self::load_v0(storage)
+                    .unwrap_or_else(|| Self::load_split::<PathV1, ColorV2>(storage)),
+            ),
+        }
+    }
+
+    fn load_v0(storage: &dyn Storage) -> Option<Self> {
         const KEY: &str = "papirs";
-        let data = storage.get(KEY).transpose().unwrap_or_else(|err| {
+        let data: DataV0 = storage.get(KEY).transpose().unwrap_or_else(|err| {
             log::error!("data found in storage but failed to load: {}", err);
             None
         })?;
         storage.remove(KEY);
-        Some(data)
+        Some(Self {
+            paths: upgrade_paths(data.paths),
+            offset: Recorder::new(data.offset),
+            tool: Recorder::new(data.tool),
+            pen_color: Recorder::new(data.pen_color.into()),
+        })
+    }
+
+    /// Loads data in version 1 or later, where each field is stored under its own key.
+    /// `P` and `C` are the types of paths and colors in that version, respectively.
+    fn load_split<P, C>(storage: &dyn Storage) -> Self
+    where
+        P: for<'de> Deserialize<'de> + Into<Path>,
+        C: for<'de> Deserialize<'de> + Default + Into<Color>,
+    {
+        macro_rules! load {
+            ($field:ident) => {
+                Model::load_field(storage, stringify!($field))
+            };
+        }
+        let data = Self {
+            paths: upgrade_paths::<P>(load!(paths)),
+            offset: load!(offset),
+            tool: load!(tool),
+            pen_color: Recorder::new(Model::load_field::<C>(storage, "pen_color").into()),
+        };
+        // the board fields are saved under the keys of a board
+        storage.remove("papirs:paths");
+        storage.remove("papirs:offset");
+        data
+    }
+}
+
+fn upgrade_paths<P>(paths: FxHashMap<PathId, P>) -> FxHashMap<PathId, Path>
+where
+    P: Into<Path>,
+{
+    (paths.into_iter())
+        .map(|(id, path)| (id, path.into()))
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{common::Board, model::persisted::Persisted, storage::MemoryStorage};
+    use serde::Serialize;
+    use std::{cell::RefCell, iter};
+
+    fn save<T>(storage: &dyn Storage, key: &str, val: &T)
+    where
+        T: Serialize,
+    {
+        assert!(Model::save_field(storage, key, val));
+    }
+
+    fn coords() -> LineString<i32> {
+        vec![(0, 0), (10, 20)].into()
+    }
+
+    /// Loads the storage twice, checking that it has been migrated to the current version
+    /// with the only path of `id`.
+    fn assert_migrated(storage: &dyn Storage, id: PathId, color: Color, width: Width) {
+        for _ in 0..2 {
+            let persisted = Persisted::load(storage);
+            assert_eq!(
+                Model::load_optional_field(storage, "version"),
+                Some(VERSION)
+            );
+            assert_eq!(persisted.paths.len(), 1);
+            let path = &persisted.paths[&id];
+            assert_eq!(path.color, color);
+            assert_eq!(path.width, width);
+            assert_eq!(path.shape, Shape::Freehand);
+            assert_eq!(path.coords, coords());
+            assert_eq!(*persisted.offset.get(), Coordinate { x: 3, y: 4 });
+            assert_eq!(*persisted.tool.get(), Tool::Eraser);
+        }
+    }
+
+    #[test]
+    fn migrates_v0() {
+        let storage: &dyn Storage = &MemoryStorage::default();
+        let id = PathId::gen();
+        let path = PathV1 {
+            color: ColorV2::Red,
+            coords: coords(),
+        };
+        let data = DataV0 {
+            paths: iter::once((id, path)).collect(),
+            offset: Coordinate { x: 3, y: 4 },
+            tool: Tool::Eraser,
+            pen_color: ColorV2::Blue,
+        };
+        storage.set("papirs", &data).unwrap();
+        assert_migrated(storage, id, Color::RED, Width::default());
+        assert!(!storage.contains("papirs"));
+    }
+
+    #[test]
+    fn migrates_v1() {
+        let storage: &dyn Storage = &MemoryStorage::default();
+        let id = PathId::gen();
+        let path = PathV1 {
+            color: ColorV2::Orange,
+            coords: coords(),
+        };
+        save(
+            storage,
+            "paths",
+            &iter::once((id, path)).collect::<FxHashMap<_, _>>(),
+        );
+        save(storage, "offset", &Coordinate { x: 3, y: 4 });
+        save(storage, "tool", &Tool::Eraser);
+        save(storage, "pen_color", &ColorV2::Blue);
+        assert_migrated(storage, id, Color::ORANGE, Width::default());
+        assert_eq!(*Persisted::load(storage).pen_color.get(), Color::BLUE);
+        assert!(!storage.contains("papirs:paths"));
+    }
+
+    #[test]
+    fn migrates_v2() {
+        let storage: &dyn Storage = &MemoryStorage::default();
+        let id = PathId::gen();
+        let path = PathV2 {
+            color: ColorV2::SkyBlue,
+            width: Width::new(8),
+            coords: coords(),
+        };
+        save(storage, "version", &2_u32);
+        save(
+            storage,
+            "paths",
+            &iter::once((id, path)).collect::<FxHashMap<_, _>>(),
+        );
+        save(storage, "offset", &Coordinate { x: 3, y: 4 });
+        save(storage, "tool", &Tool::Eraser);
+        assert_migrated(storage, id, Color::SKY_BLUE, Width::new(8));
+    }
+
+    #[test]
+    fn migrates_v3() {
+        let storage: &dyn Storage = &MemoryStorage::default();
+        let id = PathId::gen();
+        let path = PathV3 {
+            color: Color::GREEN,
+            width: Width::new(8),
+            coords: coords(),
+        };
+        save(storage, "version", &3_u32);
+        save(
+            storage,
+            "paths",
+            &iter::once((id, path)).collect::<FxHashMap<_, _>>(),
+        );
+        save(storage, "offset", &Coordinate { x: 3, y: 4 });
+        save(storage, "tool", &Tool::Eraser);
+        assert_migrated(storage, id, Color::GREEN, Width::new(8));
+    }
+
+    fn path(color: Color) -> Path {
+        Path {
+            color,
+            width: Width::new(8),
+            shape: Shape::Freehand,
+            coords: coords(),
+        }
+    }
+
+    #[test]
+    fn migrates_v4() {
+        let storage: &dyn Storage = &MemoryStorage::default();
+        let id = PathId::gen();
+        let paths = iter::once((id, path(Color::BLUE))).collect::<FxHashMap<_, _>>();
+        save(storage, "version", &4_u32);
+        save(storage, "paths", &paths);
+        save(storage, "offset", &Coordinate { x: 3, y: 4 });
+        save(storage, "tool", &Tool::Eraser);
+        assert_migrated(storage, id, Color::BLUE, Width::new(8));
+        assert!(BOARD_FIELDS_V4
+            .iter()
+            .all(|field| !storage.contains(&format!("papirs:{}", field))));
+    }
+
+    #[test]
+    fn migrates_v5() {
+        let storage: &dyn Storage = &MemoryStorage::default();
+        let boards = Boards {
+            list: vec![Board::new("A".to_owned()), Board::new("B".to_owned())],
+            active: BoardId::gen(), // fixed up on loading
+        };
+        let id = PathId::gen();
+        let other_id = PathId::gen();
+        save(storage, "version", &5_u32);
+        save(storage, "boards", &boards);
+        save(storage, "tool", &Tool::Eraser);
+        for (board, id, color) in [
+            (&boards.list[0], id, Color::RED),
+            (&boards.list[1], other_id, Color::GREEN),
+        ] {
+            let paths = iter::once((id, path(color))).collect::<FxHashMap<_, _>>();
+            save(storage, &Model::board_key(board.id, "paths"), &paths);
+            save(
+                storage,
+                &Model::board_key(board.id, "offset"),
+                &Coordinate { x: 3, y: 4 },
+            );
+        }
+        assert_migrated(storage, id, Color::RED, Width::new(8));
+        let other_paths = PathStore::read_paths::<Path>(storage, boards.list[1].id);
+        assert_eq!(other_paths[&other_id].color, Color::GREEN);
+        assert!(boards.list.iter().all(|board| {
+            !Model::contains_field(storage, &Model::board_key(board.id, "paths"))
+        }));
+    }
+
+    /// A storage which fails to save values under the keys containing `failing`.
+    #[derive(Default, Debug)]
+    struct FailingStorage {
+        inner: MemoryStorage,
+        failing: RefCell<Option<String>>,
+    }
+
+    impl Storage for FailingStorage {
+        fn get_item(&self, key: &str) -> Option<String> {
+            self.inner.get_item(key)
+        }
+
+        fn set_item(&self, key: &str, value: &str) -> anyhow::Result<()> {
+            match &*self.failing.borrow() {
+                Some(failing) if key.contains(failing.as_str()) => anyhow::bail!("quota exceeded"),
+                _ => self.inner.set_item(key, value),
+            }
+        }
+
+        fn remove(&self, key: &str) {
+            self.inner.remove(key)
+        }
+
+        fn keys(&self) -> Vec<String> {
+            self.inner.keys()
+        }
+    }
+
+    #[test]
+    fn retries_split_after_failure() {
+        let failing_storage = FailingStorage::default();
+        let storage: &dyn Storage = &failing_storage;
+        let boards = Boards {
+            list: vec![Board::new("A".to_owned()), Board::new("B".to_owned())],
+            active: BoardId::gen(),
+        };
+        let ids = [PathId::gen(), PathId::gen()];
+        save(storage, "version", &5_u32);
+        for (board, id) in boards.list.iter().zip(ids) {
+            let paths = iter::once((id, path(Color::RED))).collect::<FxHashMap<_, _>>();
+            save(storage, &Model::board_key(board.id, "paths"), &paths);
+        }
+        let key_of = |board: &Board| Model::board_key(board.id, "paths");
+
+        *failing_storage.failing.borrow_mut() = Some(boards.list[1].id.to_string());
+        split_paths(storage, &boards);
+        assert_eq!(Model::load_optional_field(storage, "version"), Some(5));
+        assert!(!Model::contains_field(storage, &key_of(&boards.list[0])));
+        assert!(Model::contains_field(storage, &key_of(&boards.list[1])));
+
+        // a path drawn on the board not split yet
+        *failing_storage.failing.borrow_mut() = None;
+        let drawn_id = PathId::gen();
+        let mut store = PathStore::new(boards.list[1].id);
+        store.mark_changed(drawn_id);
+        store.save(
+            storage,
+            &iter::once((drawn_id, path(Color::BLUE))).collect(),
+        );
+
+        split_paths(storage, &boards);
+        assert_eq!(
+            Model::load_optional_field(storage, "version"),
+            Some(VERSION)
+        );
+        for board in &boards.list {
+            assert!(!Model::contains_field(storage, &key_of(board)));
+        }
+        let paths = PathStore::read_paths::<Path>(storage, boards.list[0].id);
+        assert_eq!(paths.keys().collect::<Vec<_>>(), [&ids[0]]);
+        let paths = PathStore::new(boards.list[1].id).load::<Path>(storage);
+        assert_eq!(paths.len(), 2);
+        assert_eq!(paths[&ids[1]].color, Color::RED);
+        assert_eq!(paths[&drawn_id].color, Color::BLUE);
+    }
+
+    #[test]
+    fn keeps_paths_failing_to_split() {
+        let storage: &dyn Storage = &MemoryStorage::default();
+        let boards = Boards::default();
+        let key = Model::board_key(boards.active, "paths");
+        save(storage, "version", &5_u32);
+        storage
+            .set_item(&format!("papirs:{}", key), "broken")
+            .unwrap();
+        split_paths(storage, &boards);
+        assert_eq!(Model::load_optional_field(storage, "version"), Some(5));
+        assert_eq!(
+            storage.get_item(&format!("papirs:{}", key)).as_deref(),
+            Some("broken")
+        );
     }
 }
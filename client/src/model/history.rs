@@ -9,6 +9,11 @@ pub(super) struct History<C> {
     undo_stack: Vec<C>,
     redo_stack: Vec<C>,
     state: Option<State>,
+    /// Commands pushed since [`start_group`](Self::start_group), if called.
+    group: Option<Vec<C>>,
+    /// Whether the last command on the undo stack was pushed by
+    /// [`push_merging`](Self::push_merging) and nothing has happened since.
+    mergeable: bool,
 }
 
 impl<C> Default for History<C> {
@@ -17,12 +22,19 @@ impl<C> Default for History<C> {
             undo_stack: vec![],
             redo_stack: vec![],
             state: None,
+            group: None,
+            mergeable: false,
         }
     }
 }
 
 impl<C> History<C> {
     pub fn push(&mut self, com: C) {
+        self.mergeable = false;
+        if let Some(group) = &mut self.group {
+            group.push(com);
+            return;
+        }
         match self.state {
             None => {
                 self.undo_stack.push(com);
@@ -39,6 +51,39 @@ impl<C> History<C> {
         }
     }
 
+    /// Pushes the command, merging it into the last one by `merge` if that was also pushed
+    /// by this method just before. `merge` gives the command back if they cannot be merged.
+    pub fn push_merging(&mut self, com: C, merge: impl FnOnce(&mut C, C) -> Result<(), C>) {
+        let normal = self.state.is_none() && self.group.is_none();
+        let com = match self.undo_stack.last_mut() {
+            Some(last) if normal && self.mergeable => match merge(last, com) {
+                Ok(()) => return,
+                Err(com) => com,
+            },
+            _ => com,
+        };
+        self.push(com);
+        self.mergeable = normal;
+    }
+
+    /// Starts collecting pushed commands, which will be pushed as one command by
+    /// [`finish_group`](Self::finish_group).
+    pub fn start_group(&mut self) {
+        assert!(self.group.is_none(), "previous group not finished");
+        self.group = Some(vec![]);
+    }
+
+    /// Pushes the commands collected since [`start_group`](Self::start_group)
+    /// as one command made by `f`, unless there are none.
+    pub fn finish_group(&mut self, f: impl FnOnce(Vec<C>) -> C) {
+        let group = self.group.take().expect("no group started");
+        if group.is_empty() {
+            self.state = None;
+        } else {
+            self.push(f(group));
+        }
+    }
+
     pub fn start_undo(&mut self) -> Option<C> {
         if let Some(state) = self.state {
             panic!("previous operation not finished: {:?}", state);
@@ -46,6 +91,7 @@ impl<C> History<C> {
         let com = self.undo_stack.pop();
         if com.is_some() {
             self.state = Some(State::Undoing);
+            self.mergeable = false;
         }
         com
     }
@@ -57,6 +103,7 @@ impl<C> History<C> {
         let com = self.redo_stack.pop();
         if com.is_some() {
             self.state = Some(State::Redoing);
+            self.mergeable = false;
         }
         com
     }
@@ -0,0 +1,306 @@
+//! Incremental persistence of paths.
+
+use super::Model;
+use crate::{
+    common::{BoardId, PathId},
+    storage::Storage,
+};
+use rustc_hash::{FxHashMap, FxHashSet};
+use serde::{Deserialize, Serialize};
+use std::mem;
+
+/// The paths of a board in the storage, each of which is saved under its own key
+/// so that a change of a path writes only the path:
+///
+/// - `board:{board}:path_ids` lists the paths saved as below.
+/// - `board:{board}:path:{id}` is the path of `id`.
+/// - `board:{board}:path_log:{n}` (`n` = 0, 1, …) is the `n`-th change since the list was saved,
+///   which is the new path or a tombstone [`None`] if removed.
+///
+/// The changes are merged into the list on loading, or once the log gets long.
+#[derive(Debug)]
+pub(super) struct PathStore {
+    board: BoardId,
+    /// The paths changed since the last save.
+    unsaved_ids: FxHashSet<PathId>,
+    /// The number of the changes in the log.
+    log_len: usize,
+    /// The paths changed by the log.
+    logged_ids: FxHashSet<PathId>,
+}
+
+impl PathStore {
+    /// The number of the changes in the log beyond which they are merged.
+    const MAX_LOG_LEN: usize = 1024;
+
+    pub fn new(board: BoardId) -> Self {
+        Self {
+            board,
+            unsaved_ids: FxHashSet::default(),
+            log_len: 0,
+            logged_ids: FxHashSet::default(),
+        }
+    }
+
+    fn list_key(board: BoardId) -> String {
+        Model::board_key(board, "path_ids")
+    }
+
+    fn path_key(board: BoardId, id: PathId) -> String {
+        Model::board_key(board, &format!("path:{}", id))
+    }
+
+    fn log_key(board: BoardId, n: usize) -> String {
+        Model::board_key(board, &format!("path_log:{}", n))
+    }
+
+    /// Reads the paths of the board with the changes in the log applied,
+    /// returning the paths changed by the log and its length as well.
+    fn read<P>(storage: &dyn Storage, board: BoardId) -> (FxHashMap<PathId, P>, Vec<PathId>, usize)
+    where
+        P: for<'de> Deserialize<'de>,
+    {
+        let ids: Vec<PathId> = Model::load_field(storage, &Self::list_key(board));
+        let mut paths = (ids.into_iter())
+            .filter_map(|id| {
+                let path = Model::load_optional_field::<P>(storage, &Self::path_key(board, id));
+                if path.is_none() {
+                    log::error!("path `{}` not found in storage", id);
+                }
+                Some((id, path?))
+            })
+            .collect::<FxHashMap<_, _>>();
+        let mut logged_ids = vec![];
+        let mut log_len = 0;
+        while Model::contains_field(storage, &Self::log_key(board, log_len)) {
+            let key = Self::log_key(board, log_len);
+            if let Some((id, path)) =
+                Model::load_optional_field::<(PathId, Option<P>)>(storage, &key)
+            {
+                match path {
+                    Some(path) => paths.insert(id, path),
+                    None => paths.remove(&id),
+                };
+                logged_ids.push(id);
+            }
+            log_len += 1;
+        }
+        (paths, logged_ids, log_len)
+    }
+
+    /// Reads the paths of the board, leaving the storage as it is.
+    pub fn read_paths<P>(storage: &dyn Storage, board: BoardId) -> FxHashMap<PathId, P>
+    where
+        P: for<'de> Deserialize<'de>,
+    {
+        Self::read(storage, board).0
+    }
+
+    /// Loads the paths of the board, merging the changes in the log if any.
+    pub fn load<P>(&mut self, storage: &dyn Storage) -> FxHashMap<PathId, P>
+    where
+        P: Serialize + for<'de> Deserialize<'de>,
+    {
+        let (paths, logged_ids, log_len) = Self::read(storage, self.board);
+        self.log_len = log_len;
+        self.logged_ids.extend(logged_ids);
+        if self.log_len != 0 {
+            self.merge_log(storage, &paths);
+        }
+        paths
+    }
+
+    /// Marks the path as changed, which is saved on the next [`save`](Self::save).
+    pub fn mark_changed(&mut self, id: PathId) {
+        self.unsaved_ids.insert(id);
+    }
+
+    /// Appends the changes of the paths marked since the last save to the log.
+    pub fn save<P>(&mut self, storage: &dyn Storage, paths: &FxHashMap<PathId, P>)
+    where
+        P: Serialize,
+    {
+        for id in mem::take(&mut self.unsaved_ids) {
+            let key = Self::log_key(self.board, self.log_len);
+            // the log must not have a gap, which ends it on reading
+            if Model::save_field(storage, &key, &(id, paths.get(&id))) {
+                self.log_len += 1;
+                self.logged_ids.insert(id);
+            } else {
+                self.unsaved_ids.insert(id); // retried on the next save
+            }
+        }
+        if self.log_len > Self::MAX_LOG_LEN {
+            self.merge_log(storage, paths);
+        }
+    }
+
+    /// Saves all the paths anew, clearing the log.
+    pub fn save_all<P>(&mut self, storage: &dyn Storage, paths: &FxHashMap<PathId, P>)
+    where
+        P: Serialize,
+    {
+        self.unsaved_ids.clear();
+        self.logged_ids.extend(paths.keys().copied());
+        self.merge_log(storage, paths);
+    }
+
+    /// Saves the paths changed by the log and the list, and then clears the log if succeeded.
+    fn merge_log<P>(&mut self, storage: &dyn Storage, paths: &FxHashMap<PathId, P>)
+    where
+        P: Serialize,
+    {
+        if !Self::write(storage, self.board, paths, self.logged_ids.iter().copied()) {
+            return;
+        }
+        for n in 0..self.log_len {
+            storage.remove(&format!("papirs:{}", Self::log_key(self.board, n)));
+        }
+        self.log_len = 0;
+        self.logged_ids.clear();
+    }
+
+    /// Saves the paths of `ids` (removing those not in `paths`) and the list of `paths`,
+    /// returning whether all succeeded.
+    pub fn write<P>(
+        storage: &dyn Storage,
+        board: BoardId,
+        paths: &FxHashMap<PathId, P>,
+        ids: impl IntoIterator<Item = PathId>,
+    ) -> bool
+    where
+        P: Serialize,
+    {
+        let mut removed_ids = vec![];
+        for id in ids {
+            match paths.get(&id) {
+                Some(path) => {
+                    if !Model::save_field(storage, &Self::path_key(board, id), path) {
+                        return false;
+                    }
+                }
+                None => removed_ids.push(id),
+            }
+        }
+        let list = paths.keys().collect::<Vec<_>>();
+        if !Model::save_field(storage, &Self::list_key(board), &list) {
+            return false;
+        }
+        // removed only after unlisted
+        for id in removed_ids {
+            storage.remove(&format!("papirs:{}", Self::path_key(board, id)));
+        }
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{
+        common::{Color, Path, Shape, Width},
+        storage::MemoryStorage,
+    };
+
+    fn path(x: i32) -> Path {
+        Path {
+            color: Color::BLACK,
+            width: Width::default(),
+            shape: Shape::Freehand,
+            coords: vec![(x, 0), (x, 10)].into(),
+        }
+    }
+
+    fn log_len(storage: &dyn Storage, board: BoardId) -> usize {
+        (0..)
+            .take_while(|&n| Model::contains_field(storage, &PathStore::log_key(board, n)))
+            .count()
+    }
+
+    #[test]
+    fn replays_log() {
+        let storage: &dyn Storage = &MemoryStorage::default();
+        let board = BoardId::gen();
+        let mut store = PathStore::new(board);
+        let (a, b) = (PathId::gen(), PathId::gen());
+        let mut paths = FxHashMap::default();
+        paths.insert(a, path(0));
+        store.save_all(storage, &paths);
+
+        paths.insert(a, path(1));
+        paths.insert(b, path(2));
+        store.mark_changed(a);
+        store.mark_changed(b);
+        store.save(storage, &paths);
+        paths.insert(b, path(3));
+        store.mark_changed(b);
+        store.save(storage, &paths);
+        assert_eq!(log_len(storage, board), 3);
+        // the record of `a` is still the old one, overridden by the log
+        let record = Model::load_optional_field::<Path>(storage, &PathStore::path_key(board, a));
+        assert_eq!(record.unwrap().coords, path(0).coords);
+        let read = PathStore::read_paths::<Path>(storage, board);
+        assert_eq!(read[&a].coords, path(1).coords);
+        assert_eq!(read[&b].coords, path(3).coords);
+
+        let loaded = PathStore::new(board).load::<Path>(storage);
+        assert_eq!(loaded.len(), 2);
+        assert_eq!(loaded[&b].coords, path(3).coords);
+        assert_eq!(log_len(storage, board), 0);
+        let record = Model::load_optional_field::<Path>(storage, &PathStore::path_key(board, a));
+        assert_eq!(record.unwrap().coords, path(1).coords);
+    }
+
+    #[test]
+    fn removes_paths_by_tombstones() {
+        let storage: &dyn Storage = &MemoryStorage::default();
+        let board = BoardId::gen();
+        let mut store = PathStore::new(board);
+        let (a, b) = (PathId::gen(), PathId::gen());
+        let mut paths = FxHashMap::default();
+        paths.insert(a, path(0));
+        paths.insert(b, path(1));
+        store.save_all(storage, &paths);
+
+        paths.remove(&a);
+        store.mark_changed(a);
+        store.save(storage, &paths);
+        let read = PathStore::read_paths::<Path>(storage, board);
+        assert_eq!(read.keys().collect::<Vec<_>>(), [&b]);
+
+        let loaded = PathStore::new(board).load::<Path>(storage);
+        assert_eq!(loaded.keys().collect::<Vec<_>>(), [&b]);
+        assert!(!Model::contains_field(
+            storage,
+            &PathStore::path_key(board, a)
+        ));
+        assert_eq!(log_len(storage, board), 0);
+    }
+
+    #[test]
+    fn merges_long_log() {
+        let storage: &dyn Storage = &MemoryStorage::default();
+        let board = BoardId::gen();
+        let mut store = PathStore::new(board);
+        let mut paths = FxHashMap::default();
+        for x in 0..=PathStore::MAX_LOG_LEN as i32 {
+            let id = PathId::gen();
+            paths.insert(id, path(x));
+            store.mark_changed(id);
+            store.save(storage, &paths);
+            let len = if paths.len() > PathStore::MAX_LOG_LEN {
+                0
+            } else {
+                paths.len()
+            };
+            assert_eq!(log_len(storage, board), len);
+        }
+        let list: Vec<PathId> = Model::load_field(storage, &PathStore::list_key(board));
+        assert_eq!(list.len(), PathStore::MAX_LOG_LEN + 1);
+        let read = PathStore::read_paths::<Path>(storage, board);
+        assert_eq!(read.len(), PathStore::MAX_LOG_LEN + 1);
+        assert!(paths
+            .iter()
+            .all(|(id, path)| read[id].coords == path.coords));
+    }
+}
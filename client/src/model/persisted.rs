@@ -0,0 +1,301 @@
+//! Loading of the fields persisted in the storage, which needs no view.
+
+use super::{compat, path_store::PathStore, Model, Recorder};
+use crate::{
+    common::{
+        Boards, Color, DocLayerId, DocLayers, EraserRadius, FontSize, GroupId, ImageData,
+        ImageHash, LassoMode, Palette, Path, PathId, Scale, Shape, Tool, Width,
+    },
+    storage::Storage,
+};
+use geo::Coordinate;
+use itertools::Itertools as _;
+use rustc_hash::{FxHashMap, FxHashSet};
+
+/// The fields of the model loaded from the storage, with the active board of `boards`.
+#[derive(Debug)]
+pub(super) struct Persisted {
+    pub boards: Recorder<Boards>,
+    pub paths: FxHashMap<PathId, Path>,
+    pub path_store: PathStore,
+    pub path_layers: Recorder<FxHashMap<PathId, DocLayerId>>,
+    pub doc_layers: Recorder<DocLayers>,
+    pub path_orders: Recorder<FxHashMap<PathId, i64>>,
+    pub path_groups: Recorder<FxHashMap<PathId, GroupId>>,
+    pub locked_path_ids: Recorder<FxHashSet<PathId>>,
+    pub offset: Recorder<Coordinate<i32>>,
+    pub scale: Recorder<Scale>,
+    pub tool: Recorder<Tool>,
+    pub pen_color: Recorder<Color>,
+    pub pen_width: Recorder<Width>,
+    pub palette: Recorder<Palette>,
+    pub font_size: Recorder<FontSize>,
+    pub eraser_radius: Recorder<EraserRadius>,
+    pub lasso_mode: Recorder<LassoMode>,
+    /// The stored images which the paths refer to.
+    pub images: FxHashMap<ImageHash, ImageData>,
+}
+
+impl Persisted {
+    /// Loads the fields, migrating the storage from an old format if needed.
+    pub fn load(storage: &dyn Storage) -> Self {
+        macro_rules! load {
+            ($field:ident) => {
+                Model::load_field(storage, stringify!($field))
+            };
+        }
+
+        let mut boards: Recorder<Boards> = load!(boards);
+        if boards.get().list.is_empty() {
+            *boards.get_mut() = Boards::default();
+        }
+        if boards.get().get(boards.get().active).is_none() {
+            let first = boards.get().list[0].id;
+            boards.get_mut().active = first;
+        }
+        if boards.is_updated() || !Model::contains_field(storage, "boards") {
+            Model::save_field(storage, "boards", &boards);
+            boards.resolve();
+        }
+        let board = boards.get().active;
+        macro_rules! load_board {
+            ($field:ident) => {
+                Model::load_field(storage, &Model::board_key(board, stringify!($field)))
+            };
+        }
+
+        compat::move_into_board(storage, board);
+        compat::split_paths(storage, boards.get());
+        let old_data = compat::Data::load_and_remove(storage);
+        let needs_to_save = old_data.is_some();
+
+        let mut path_store = PathStore::new(board);
+        let (paths, offset, tool, pen_color) = old_data.map_or_else(
+            || {
+                (
+                    path_store.load(storage),
+                    load_board!(offset),
+                    load!(tool),
+                    load!(pen_color),
+                )
+            },
+            |data| (data.paths, data.offset, data.tool, data.pen_color),
+        );
+        let scale: Recorder<Scale> = load_board!(scale);
+        let pen_width: Recorder<Width> = load!(pen_width);
+        let palette: Recorder<Palette> = load!(palette);
+        let font_size: Recorder<FontSize> = load!(font_size);
+        let eraser_radius: Recorder<EraserRadius> = load!(eraser_radius);
+        let lasso_mode: Recorder<LassoMode> = load!(lasso_mode);
+        let mut doc_layers: Recorder<DocLayers> = load_board!(doc_layers);
+        let mut path_layers: Recorder<FxHashMap<PathId, DocLayerId>> = load_board!(path_layers);
+        if doc_layers.get().list.is_empty() {
+            *doc_layers.get_mut() = DocLayers::default();
+        }
+        if doc_layers.get().get(doc_layers.get().active).is_none() {
+            let bottom = doc_layers.get().list[0].id;
+            doc_layers.get_mut().active = bottom;
+        }
+        path_layers.update(|l| {
+            let prev_len = l.len();
+            l.retain(|id, layer| paths.contains_key(id) && doc_layers.get().get(*layer).is_some());
+            l.len() != prev_len
+        });
+        let mut path_orders: Recorder<FxHashMap<PathId, i64>> = load_board!(path_orders);
+        path_orders.update(|o| {
+            let prev_len = o.len();
+            o.retain(|id, _| paths.contains_key(id));
+            // the paths saved without orders are stacked as they have been rendered
+            let unordered = (paths.iter())
+                .filter(|(id, _)| !o.contains_key(id))
+                .sorted_by_key(|&(&id, path)| (!path.is_background(), id))
+                .map(|(&id, _)| id)
+                .collect::<Vec<_>>();
+            let next = o.values().max().map_or(0, |&max| max + 1);
+            let is_updated = o.len() != prev_len || !unordered.is_empty();
+            o.extend(unordered.into_iter().zip(next..));
+            is_updated
+        });
+        let mut path_groups: Recorder<FxHashMap<PathId, GroupId>> = load_board!(path_groups);
+        path_groups.update(|g| {
+            let prev_len = g.len();
+            g.retain(|id, _| paths.contains_key(id));
+            g.len() != prev_len
+        });
+        let mut locked_path_ids: Recorder<FxHashSet<PathId>> = load_board!(locked_path_ids);
+        locked_path_ids.update(|l| {
+            let prev_len = l.len();
+            l.retain(|id| paths.contains_key(id));
+            l.len() != prev_len
+        });
+        let images = Self::load_images(storage, boards.get(), &paths);
+
+        let mut persisted = Self {
+            boards,
+            paths,
+            path_store,
+            path_layers,
+            doc_layers,
+            path_orders,
+            path_groups,
+            locked_path_ids,
+            offset,
+            scale,
+            tool,
+            pen_color,
+            pen_width,
+            palette,
+            font_size,
+            eraser_radius,
+            lasso_mode,
+            images,
+        };
+        if needs_to_save {
+            persisted.save_all(storage);
+        }
+        persisted
+    }
+
+    /// Loads the images referenced by the paths of the active board,
+    /// removing the stored images no longer referenced by any board.
+    fn load_images(
+        storage: &dyn Storage,
+        boards: &Boards,
+        paths: &FxHashMap<PathId, Path>,
+    ) -> FxHashMap<ImageHash, ImageData> {
+        let image_hashes = |paths: &mut dyn Iterator<Item = &Path>| {
+            paths
+                .filter_map(|path| match path.shape {
+                    Shape::Image(hash) => Some(hash),
+                    _ => None,
+                })
+                .collect::<FxHashSet<_>>()
+        };
+        let hashes = image_hashes(&mut paths.values());
+        let images = (hashes.iter())
+            .filter_map(|&hash| {
+                let data =
+                    Model::load_optional_field::<ImageData>(storage, &Model::image_key(hash));
+                if data.is_none() {
+                    log::error!("image `{}` not found in storage", hash);
+                }
+                Some((hash, data?))
+            })
+            .collect();
+        let mut keys = hashes
+            .iter()
+            .map(|&hash| format!("papirs:{}", Model::image_key(hash)))
+            .collect::<FxHashSet<_>>();
+        for board in boards.list.iter().filter(|board| board.id != boards.active) {
+            let paths = PathStore::read_paths::<Path>(storage, board.id);
+            let hashes = image_hashes(&mut paths.values());
+            keys.extend(
+                hashes
+                    .into_iter()
+                    .map(|hash| format!("papirs:{}", Model::image_key(hash))),
+            );
+        }
+        for key in storage.keys() {
+            if key.starts_with("papirs:image:") && !keys.contains(&key) {
+                storage.remove(&key);
+            }
+        }
+        images
+    }
+
+    /// Saves all the fields in the current format.
+    fn save_all(&mut self, storage: &dyn Storage) {
+        let board = self.boards.get().active;
+        macro_rules! save {
+            ($field:ident) => {
+                Model::save_field(storage, stringify!($field), &self.$field);
+            };
+        }
+        macro_rules! save_board {
+            ($field:ident) => {
+                Model::save_field(
+                    storage,
+                    &Model::board_key(board, stringify!($field)),
+                    &self.$field,
+                );
+            };
+        }
+        Model::save_field(storage, "version", &compat::VERSION);
+        save!(boards);
+        self.path_store.save_all(storage, &self.paths);
+        save_board!(offset);
+        save_board!(scale);
+        save!(tool);
+        save!(pen_color);
+        save!(pen_width);
+        save!(palette);
+        save!(font_size);
+        save!(eraser_radius);
+        save!(lasso_mode);
+        save_board!(doc_layers);
+        save_board!(path_layers);
+        save_board!(path_orders);
+        save_board!(path_groups);
+        save_board!(locked_path_ids);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::storage::MemoryStorage;
+
+    #[test]
+    fn reloads_saved_fields() {
+        let storage: &dyn Storage = &MemoryStorage::default();
+        let mut persisted = Persisted::load(storage);
+        assert!(persisted.paths.is_empty());
+
+        let data = ImageData {
+            mime_type: "image/png".to_owned(),
+            bytes: vec![1, 2, 3],
+        };
+        let hash = data.hash();
+        Model::save_field(storage, &Model::image_key(hash), &data);
+        let id = PathId::gen();
+        let path = Path {
+            color: Color::BLUE,
+            width: Width::new(8),
+            shape: Shape::Image(hash),
+            coords: vec![(0, 0), (10, 20)].into(),
+        };
+        persisted.paths.insert(id, path);
+        persisted.path_store.mark_changed(id);
+        persisted.path_store.save(storage, &persisted.paths);
+        let board = persisted.boards.get().active;
+        Model::save_field(
+            storage,
+            &Model::board_key(board, "offset"),
+            &Coordinate { x: 3, y: 4 },
+        );
+
+        let persisted = Persisted::load(storage);
+        assert_eq!(persisted.boards.get().active, board);
+        assert_eq!(persisted.paths[&id].shape, Shape::Image(hash));
+        assert_eq!(
+            *persisted.path_orders.get(),
+            [(id, 0)].into_iter().collect()
+        );
+        assert_eq!(*persisted.offset.get(), Coordinate { x: 3, y: 4 });
+        assert_eq!(persisted.images.get(&hash), Some(&data));
+    }
+
+    #[test]
+    fn removes_unreferenced_images() {
+        let storage: &dyn Storage = &MemoryStorage::default();
+        let data = ImageData {
+            mime_type: "image/png".to_owned(),
+            bytes: vec![1, 2, 3],
+        };
+        let key = Model::image_key(data.hash());
+        Model::save_field(storage, &key, &data);
+        let persisted = Persisted::load(storage);
+        assert!(persisted.images.is_empty());
+        assert!(!Model::contains_field(storage, &key));
+    }
+}
@@ -10,6 +10,13 @@ pub(super) struct Recorder<T> {
 }
 
 impl<T> Recorder<T> {
+    pub fn new(inner: T) -> Self {
+        Self {
+            inner,
+            is_updated: false,
+        }
+    }
+
     pub fn get(&self) -> &T {
         &self.inner
     }
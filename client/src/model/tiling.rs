@@ -1,5 +1,8 @@
-use crate::{common::PathId, utils};
-use geo::{prelude::*, Coordinate, Line, LineString, Rect};
+use crate::{
+    common::{PathId, RenderablePath},
+    utils,
+};
+use geo::{prelude::*, Coordinate, Line, Rect};
 use itertools::Itertools as _;
 use rustc_hash::{FxHashMap, FxHashSet};
 use std::iter::FromIterator;
@@ -13,14 +16,14 @@ pub(super) struct Tiling {
     tile_ids: FxHashMap<PathId, Vec<TileId>>,
 }
 
-impl<'a> FromIterator<(PathId, &'a LineString<i32>)> for Tiling {
+impl<'a> FromIterator<(PathId, &'a RenderablePath)> for Tiling {
     fn from_iter<I>(iter: I) -> Self
     where
-        I: IntoIterator<Item = (PathId, &'a LineString<i32>)>,
+        I: IntoIterator<Item = (PathId, &'a RenderablePath)>,
     {
         let mut this = Self::default();
-        for (path_id, coords) in iter {
-            this.insert_path(path_id, coords);
+        for (path_id, path) in iter {
+            this.insert_path(path_id, path);
         }
         this
     }
@@ -36,29 +39,50 @@ impl Tiling {
         )
     }
 
-    fn bounding_tile_ids(
-        geo: impl BoundingRect<i32, Output = Rect<i32>> + Intersects<Rect<i32>>,
-    ) -> impl Iterator<Item = TileId> {
-        let rect = geo.bounding_rect();
-        let xs = rect.min().x.div_euclid(Self::TILE_LEN)..=rect.max().x.div_euclid(Self::TILE_LEN);
-        let ys = rect.min().y.div_euclid(Self::TILE_LEN)..=rect.max().y.div_euclid(Self::TILE_LEN);
-        xs.cartesian_product(ys)
+    fn bounding_tile_ids<G>(geo: G) -> impl Iterator<Item = TileId>
+    where
+        G: BoundingRect<i32> + Intersects<Rect<i32>>,
+        G::Output: Into<Option<Rect<i32>>>,
+    {
+        let rect = geo.bounding_rect().into();
+        rect.into_iter()
+            .flat_map(|rect: Rect<i32>| {
+                let xs = rect.min().x.div_euclid(Self::TILE_LEN)
+                    ..=rect.max().x.div_euclid(Self::TILE_LEN);
+                let ys = rect.min().y.div_euclid(Self::TILE_LEN)
+                    ..=rect.max().y.div_euclid(Self::TILE_LEN);
+                xs.cartesian_product(ys)
+            })
             .map(|(x, y)| TileId(Coordinate { x, y }))
             .filter(move |&id| geo.intersects(&Self::tile_rect(id)))
     }
 
-    pub fn bounding_tile_items(
-        &self,
-        geo: impl BoundingRect<i32, Output = Rect<i32>> + Intersects<Rect<i32>>,
-    ) -> impl Iterator<Item = (PathId, &[Line<i32>])> {
+    pub fn bounding_tile_items<G>(&self, geo: G) -> impl Iterator<Item = (PathId, &[Line<i32>])>
+    where
+        G: BoundingRect<i32> + Intersects<Rect<i32>>,
+        G::Output: Into<Option<Rect<i32>>>,
+    {
         Self::bounding_tile_ids(geo)
             .flat_map(move |tile_id| self.tiles.get(&tile_id).into_iter().flatten())
             .map(|(&path_id, lines)| (path_id, lines.as_slice()))
     }
 
-    pub fn insert_path(&mut self, path_id: PathId, coords: &LineString<i32>) {
+    /// Registers the lines of the outline of the path to the tiles they pass through.
+    ///
+    /// If the path is filled, it is also registered without lines to the tiles its area covers.
+    pub fn insert_path(&mut self, path_id: PathId, path: &RenderablePath) {
         let mut tile_ids = FxHashSet::default();
-        for line in coords.lines() {
+        if let Some(area) = path.area() {
+            for tile_id in Self::bounding_tile_ids(area) {
+                self.tiles
+                    .entry(tile_id)
+                    .or_default()
+                    .entry(path_id)
+                    .or_default();
+                tile_ids.insert(tile_id);
+            }
+        }
+        for line in path.outline().lines() {
             for tile_id in Self::bounding_tile_ids(line) {
                 self.tiles
                     .entry(tile_id)
@@ -84,9 +108,4 @@ impl Tiling {
                 .retain(|id, _| id != &path_id);
         }
     }
-
-    pub fn clear(&mut self) {
-        self.tiles.clear();
-        self.tile_ids.clear();
-    }
 }
@@ -0,0 +1,367 @@
+//! Rasterization of paths into PNG images, independent of the browser.
+
+use crate::common::{Color, ImageData, ImageHash, Path, Segment, Shape, Text};
+use anyhow::{anyhow, bail, Result};
+use geo::{Coordinate, Rect};
+use rustc_hash::FxHashMap;
+use tiny_skia::{
+    FillRule, LineCap, LineJoin, Paint, PathBuilder, Pixmap, PixmapPaint, Stroke, Transform,
+};
+use ttf_parser::{Face, GlyphId, OutlineBuilder};
+
+/// Builds the path of the segments.
+fn path_of(segments: &[Segment]) -> Option<tiny_skia::Path> {
+    let f = |c: Coordinate<f64>| (c.x as f32, c.y as f32);
+    let mut pb = PathBuilder::new();
+    for segment in segments {
+        match *segment {
+            Segment::MoveTo(c) => {
+                let (x, y) = f(c);
+                pb.move_to(x, y);
+            }
+            Segment::LineTo(c) => {
+                let (x, y) = f(c);
+                pb.line_to(x, y);
+            }
+            Segment::QuadTo(c_1, end) => {
+                let ((x1, y1), (x, y)) = (f(c_1), f(end));
+                pb.quad_to(x1, y1, x, y);
+            }
+            Segment::CubicTo(c_1, c_2, end) => {
+                let ((x1, y1), (x2, y2), (x, y)) = (f(c_1), f(c_2), f(end));
+                pb.cubic_to(x1, y1, x2, y2, x, y);
+            }
+            Segment::Close => pb.close(),
+        }
+    }
+    pb.finish()
+}
+
+/// Builds the outline of a glyph whose baseline starts at `origin`, scaled from font units.
+struct GlyphOutline<'a> {
+    pb: &'a mut PathBuilder,
+    origin: Coordinate<f32>,
+    scale: f32,
+}
+
+impl GlyphOutline<'_> {
+    fn map(&self, x: f32, y: f32) -> (f32, f32) {
+        (
+            self.origin.x + x * self.scale,
+            self.origin.y - y * self.scale,
+        )
+    }
+}
+
+impl OutlineBuilder for GlyphOutline<'_> {
+    fn move_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.map(x, y);
+        self.pb.move_to(x, y);
+    }
+
+    fn line_to(&mut self, x: f32, y: f32) {
+        let (x, y) = self.map(x, y);
+        self.pb.line_to(x, y);
+    }
+
+    fn quad_to(&mut self, x1: f32, y1: f32, x: f32, y: f32) {
+        let ((x1, y1), (x, y)) = (self.map(x1, y1), self.map(x, y));
+        self.pb.quad_to(x1, y1, x, y);
+    }
+
+    fn curve_to(&mut self, x1: f32, y1: f32, x2: f32, y2: f32, x: f32, y: f32) {
+        let ((x1, y1), (x2, y2), (x, y)) = (self.map(x1, y1), self.map(x2, y2), self.map(x, y));
+        self.pb.cubic_to(x1, y1, x2, y2, x, y);
+    }
+
+    fn close(&mut self) {
+        self.pb.close();
+    }
+}
+
+/// Builds the outlines of the text laid out in the same way as [`crate::web::Canvas::fill_text`],
+/// returning them with the size of the text.
+fn text_path(face: &Face<'_>, text: &Text) -> (Option<tiny_skia::Path>, Coordinate<f64>) {
+    let scale = text.font_size.get() as f32 / f32::from(face.units_per_em());
+    let ascender = f32::from(face.ascender()) * scale;
+    let mut pb = PathBuilder::new();
+    let mut width = 0f32;
+    for (i, line) in text.lines().enumerate() {
+        let mut origin = Coordinate {
+            x: 0.,
+            y: (i as f64 * text.line_height()) as f32 + ascender,
+        };
+        for c in line.chars() {
+            let glyph = face.glyph_index(c).unwrap_or(GlyphId(0));
+            let mut outline = GlyphOutline {
+                pb: &mut pb,
+                origin,
+                scale,
+            };
+            face.outline_glyph(glyph, &mut outline);
+            origin.x += f32::from(face.glyph_hor_advance(glyph).unwrap_or(0)) * scale;
+        }
+        width = width.max(origin.x);
+    }
+    let size = Coordinate {
+        x: f64::from(width),
+        y: text.lines().count() as f64 * text.line_height(),
+    };
+    (pb.finish(), size)
+}
+
+/// Decodes the JPEG image into an opaque pixmap.
+fn decode_jpeg(bytes: &[u8]) -> Result<Pixmap> {
+    let mut decoder = jpeg_decoder::Decoder::new(bytes);
+    let pixels = decoder.decode()?;
+    let info = decoder.info().ok_or_else(|| anyhow!("no image info"))?;
+    let mut pixmap = Pixmap::new(info.width.into(), info.height.into())
+        .ok_or_else(|| anyhow!("invalid size: {}x{}", info.width, info.height))?;
+    let dst = pixmap.data_mut().chunks_exact_mut(4);
+    match info.pixel_format {
+        jpeg_decoder::PixelFormat::L8 => {
+            for (dst, &l) in dst.zip(&pixels) {
+                dst.copy_from_slice(&[l, l, l, u8::MAX]);
+            }
+        }
+        jpeg_decoder::PixelFormat::RGB24 => {
+            for (dst, src) in dst.zip(pixels.chunks_exact(3)) {
+                dst.copy_from_slice(&[src[0], src[1], src[2], u8::MAX]);
+            }
+        }
+        format => bail!("unsupported pixel format: {:?}", format),
+    }
+    Ok(pixmap)
+}
+
+fn decode_image(data: &ImageData) -> Result<Pixmap> {
+    match &*data.mime_type {
+        "image/png" => Ok(Pixmap::decode_png(&data.bytes)?),
+        "image/jpeg" => decode_jpeg(&data.bytes),
+        mime_type => bail!("unsupported image type: {}", mime_type),
+    }
+}
+
+/// Returns the transformation which maps the rectangle `(0, 0)`-`size` onto
+/// the parallelogram with the corner `origin` and the two edges `u` and `v` from it.
+fn transform_onto(
+    size: Coordinate<f64>,
+    origin: Coordinate<f64>,
+    u: Coordinate<f64>,
+    v: Coordinate<f64>,
+) -> Transform {
+    let (width, height) = (size.x.max(1.), size.y.max(1.));
+    Transform::from_row(
+        (u.x / width) as f32,
+        (u.y / width) as f32,
+        (v.x / height) as f32,
+        (v.y / height) as f32,
+        origin.x as f32,
+        origin.y as f32,
+    )
+}
+
+fn paint_of(color: Color) -> Paint<'static> {
+    let mut paint = Paint::default();
+    let (r, g, b) = color.rgb();
+    paint.set_color_rgba8(r, g, b, color.alpha());
+    paint.anti_alias = true;
+    paint
+}
+
+fn render_path(
+    pixmap: &mut Pixmap,
+    path: &Path,
+    images: &FxHashMap<ImageHash, ImageData>,
+    face: &Face<'_>,
+    transform: Transform,
+) {
+    let obj = match &path.shape {
+        Shape::Text(text) => {
+            let (origin, u, v) = match path.parallelogram() {
+                Some(p) => p,
+                None => return,
+            };
+            let (obj, size) = text_path(face, text);
+            if let Some(obj) = obj {
+                let transform = transform.pre_concat(transform_onto(size, origin, u, v));
+                let paint = paint_of(path.color);
+                pixmap.fill_path(&obj, &paint, FillRule::Winding, transform, None);
+            }
+            return;
+        }
+        Shape::Image(hash) => {
+            let (data, (origin, u, v)) = match (images.get(hash), path.parallelogram()) {
+                (Some(data), Some(p)) => (data, p),
+                _ => return,
+            };
+            let image = match decode_image(data) {
+                Ok(image) => image,
+                Err(err) => return log::error!("failed to decode image `{}`: {}", hash, err),
+            };
+            let size = Coordinate {
+                x: f64::from(image.width()),
+                y: f64::from(image.height()),
+            };
+            let paint = PixmapPaint {
+                quality: tiny_skia::FilterQuality::Bilinear,
+                ..PixmapPaint::default()
+            };
+            let transform = transform.pre_concat(transform_onto(size, origin, u, v));
+            pixmap.draw_pixmap(0, 0, image.as_ref(), &paint, transform, None);
+            return;
+        }
+        Shape::Freehand | Shape::Line | Shape::Arrow | Shape::Rectangle | Shape::Ellipse => {
+            path_of(&path.segments())
+        }
+    };
+    let obj = match obj {
+        Some(obj) => obj,
+        None => return,
+    };
+    let paint = paint_of(path.color);
+    let stroke = Stroke {
+        width: path.width.get() as f32,
+        line_cap: LineCap::Round,
+        line_join: LineJoin::Round,
+        ..Stroke::default()
+    };
+    pixmap.stroke_path(&obj, &paint, &stroke, transform, None);
+}
+
+/// Renders the part `rect` of the board with the paths given from the bottom into a PNG image,
+/// scaled by `scale` and over `background` if any.
+///
+/// Texts are rendered with `font` (a TrueType or OpenType font) in place of `sans-serif`
+/// of the browser, so they may look slightly different from the board.
+pub fn render_png<'a>(
+    paths: impl IntoIterator<Item = &'a Path>,
+    images: &FxHashMap<ImageHash, ImageData>,
+    font: &[u8],
+    rect: Rect<i32>,
+    scale: f64,
+    background: Option<Color>,
+) -> Result<Vec<u8>> {
+    let width = (f64::from(rect.width()) * scale).ceil() as u32;
+    let height = (f64::from(rect.height()) * scale).ceil() as u32;
+    let mut pixmap = Pixmap::new(width.max(1), height.max(1))
+        .ok_or_else(|| anyhow!("image too large: {}x{}", width, height))?;
+    if let Some(color) = background {
+        let (r, g, b) = color.rgb();
+        pixmap.fill(tiny_skia::Color::from_rgba8(r, g, b, color.alpha()));
+    }
+    let transform = Transform::from_row(
+        scale as f32,
+        0.,
+        0.,
+        scale as f32,
+        (-f64::from(rect.min().x) * scale) as f32,
+        (-f64::from(rect.min().y) * scale) as f32,
+    );
+    let face = Face::from_slice(font, 0).map_err(|err| anyhow!("invalid font: {}", err))?;
+    for path in paths {
+        render_path(&mut pixmap, path, images, &face, transform);
+    }
+    pixmap
+        .encode_png()
+        .map_err(|err| anyhow!("failed to encode the image: {}", err))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::common::{FontSize, Width};
+
+    static FONT: &[u8] = include_bytes!("../assets/dejavu-fonts/DejaVuSans.ttf");
+
+    fn render(paths: &[Path], images: &FxHashMap<ImageHash, ImageData>, scale: f64) -> Pixmap {
+        let rect = Rect::new((0, 0), (20, 10));
+        let png = render_png(paths, images, FONT, rect, scale, Some(Color::WHITE)).unwrap();
+        Pixmap::decode_png(&png).unwrap()
+    }
+
+    fn rgb(pixmap: &Pixmap, x: u32, y: u32) -> (u8, u8, u8) {
+        let pixel = pixmap.pixel(x, y).unwrap();
+        (pixel.red(), pixel.green(), pixel.blue())
+    }
+
+    /// A path of the shape spanning the rectangle `(0, 0)`-`(20, 10)`.
+    fn filling_path(shape: Shape) -> Path {
+        Path {
+            color: Color::BLACK,
+            width: Width::default(),
+            shape,
+            coords: vec![(0, 0), (20, 0), (20, 10), (0, 10)].into(),
+        }
+    }
+
+    #[test]
+    fn renders_strokes() {
+        let path = Path {
+            color: Color::BLACK,
+            width: Width::new(4),
+            shape: Shape::Line,
+            coords: vec![(2, 5), (18, 5)].into(),
+        };
+        let pixmap = render(&[path], &FxHashMap::default(), 2.);
+        assert_eq!((pixmap.width(), pixmap.height()), (40, 20));
+        assert_eq!(rgb(&pixmap, 20, 10), (0, 0, 0));
+        assert_eq!(rgb(&pixmap, 20, 1), (255, 255, 255));
+    }
+
+    #[test]
+    fn renders_texts() {
+        let text = |content: &str| {
+            filling_path(Shape::Text(Text {
+                content: content.to_owned(),
+                font_size: FontSize::new(16),
+            }))
+        };
+        let dark_pixels =
+            |pixmap: &Pixmap| pixmap.pixels().iter().filter(|p| p.red() < 128).count();
+        let blank = render(&[text("")], &FxHashMap::default(), 1.);
+        assert_eq!(dark_pixels(&blank), 0);
+        let pixmap = render(&[text("I")], &FxHashMap::default(), 1.);
+        // stretched over the whole rectangle
+        assert_eq!(rgb(&pixmap, 10, 5), (0, 0, 0));
+        assert_eq!(rgb(&pixmap, 1, 5), (255, 255, 255));
+        let lines = render(&[text("I\nI\nI\nI")], &FxHashMap::default(), 1.);
+        assert!(dark_pixels(&lines) < dark_pixels(&pixmap));
+    }
+
+    #[test]
+    fn renders_images() {
+        let mut image = Pixmap::new(4, 4).unwrap();
+        image.fill(tiny_skia::Color::from_rgba8(0, 0, 255, 255));
+        let png = ImageData {
+            mime_type: "image/png".to_owned(),
+            bytes: image.encode_png().unwrap(),
+        };
+        // 8x8 pixels of red
+        let jpeg = ImageData {
+            mime_type: "image/jpeg".to_owned(),
+            bytes: base64::decode(
+                "/9j/4AAQSkZJRgABAgAAAQABAAD/wAARCAAIAAgDABEAAREBAhEB/9sAQwADAgIDAgIDAwMDBAMDBAUI\
+                BQUEBAUKBwcGCAwKDAwLCgsLDQ4SEA0OEQ4LCxAWEBETFBUVFQwPFxgWFBgSFBUU/9sAQwEDBAQFBAUJ\
+                BQUJFA0LDRQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQUFBQU/8QA\
+                HwAAAQUBAQEBAQEAAAAAAAAAAAECAwQFBgcICQoL/8QAtRAAAgEDAwIEAwUFBAQAAAF9AQIDAAQRBRIh\
+                MUEGE1FhByJxFDKBkaEII0KxwRVS0fAkM2JyggkKFhcYGRolJicoKSo0NTY3ODk6Q0RFRkdISUpTVFVW\
+                V1hZWmNkZWZnaGlqc3R1dnd4eXqDhIWGh4iJipKTlJWWl5iZmqKjpKWmp6ipqrKztLW2t7i5usLDxMXG\
+                x8jJytLT1NXW19jZ2uHi4+Tl5ufo6erx8vP09fb3+Pn6/8QAHwEAAwEBAQEBAQEBAQAAAAAAAAECAwQF\
+                BgcICQoL/8QAtREAAgECBAQDBAcFBAQAAQJ3AAECAxEEBSExBhJBUQdhcRMiMoEIFEKRobHBCSMzUvAV\
+                YnLRChYkNOEl8RcYGRomJygpKjU2Nzg5OkNERUZHSElKU1RVVldYWVpjZGVmZ2hpanN0dXZ3eHl6goOE\
+                hYaHiImKkpOUlZaXmJmaoqOkpaanqKmqsrO0tba3uLm6wsPExcbHyMnK0tPU1dbX2Nna4uPk5ebn6Onq\
+                8vP09fb3+Pn6/9oADAMAAAERAhEAPwD50r8MP9Uz/9k=",
+            )
+            .unwrap(),
+        };
+        for (data, color) in [(png, (0, 0, 255)), (jpeg, (255, 0, 0))] {
+            let hash = data.hash();
+            let images = [(hash, data)].into_iter().collect();
+            let pixmap = render(&[filling_path(Shape::Image(hash))], &images, 1.);
+            let (r, g, b) = rgb(&pixmap, 10, 5);
+            let diff = |a: u8, b: u8| (i32::from(a) - i32::from(b)).abs();
+            assert!(diff(r, color.0) < 8 && diff(g, color.1) < 8 && diff(b, color.2) < 8);
+        }
+    }
+}
@@ -0,0 +1,76 @@
+//! Key-value stores where the model persists its fields.
+
+use crate::web;
+use anyhow::Result;
+use serde::{Deserialize, Serialize};
+use std::{cell::RefCell, collections::BTreeMap, fmt};
+
+/// A key-value store of texts.
+pub trait Storage: fmt::Debug {
+    fn get_item(&self, key: &str) -> Option<String>;
+
+    fn set_item(&self, key: &str, value: &str) -> Result<()>;
+
+    fn remove(&self, key: &str);
+
+    fn keys(&self) -> Vec<String>;
+}
+
+impl dyn Storage + '_ {
+    pub fn get<T>(&self, key: &str) -> Option<Result<T>>
+    where
+        T: for<'de> Deserialize<'de>,
+    {
+        self.get_item(key).map(|s| Ok(web::decode(&s)?))
+    }
+
+    pub fn set<T>(&self, key: &str, val: &T) -> Result<()>
+    where
+        T: Serialize,
+    {
+        thread_local! {
+            static BUF: RefCell<String> = RefCell::new(String::new());
+        }
+        BUF.with(|s| {
+            let s = &mut *s.borrow_mut();
+            s.clear();
+            web::encode_into(val, s)?;
+            self.set_item(key, s)
+        })
+    }
+
+    pub fn contains(&self, key: &str) -> bool {
+        self.get_item(key).is_some()
+    }
+
+    /// Copies the value under the key `from` to the key `to`, if any.
+    pub fn copy(&self, from: &str, to: &str) -> Result<()> {
+        match self.get_item(from) {
+            Some(s) => self.set_item(to, &s),
+            None => Ok(()),
+        }
+    }
+}
+
+/// A storage which lives only as long as the page, where nothing is persisted.
+#[derive(Default, Debug)]
+pub struct MemoryStorage(RefCell<BTreeMap<String, String>>);
+
+impl Storage for MemoryStorage {
+    fn get_item(&self, key: &str) -> Option<String> {
+        self.0.borrow().get(key).cloned()
+    }
+
+    fn set_item(&self, key: &str, value: &str) -> Result<()> {
+        self.0.borrow_mut().insert(key.to_owned(), value.to_owned());
+        Ok(())
+    }
+
+    fn remove(&self, key: &str) {
+        self.0.borrow_mut().remove(key);
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.0.borrow().keys().cloned().collect()
+    }
+}
@@ -0,0 +1,177 @@
+//! SVG serialization and parsing of paths, for other applications.
+
+mod parse;
+
+pub use self::parse::parse;
+
+use crate::{
+    common::{Color, ImageData, ImageHash, RenderablePath, Segment, Shape, Text},
+    utils,
+};
+use geo::Coordinate;
+use rustc_hash::FxHashMap;
+use std::fmt::{self, Write as _};
+
+/// Escapes the characters which cannot appear in XML text or attribute values.
+fn escape(s: &str) -> String {
+    let mut escaped = String::with_capacity(s.len());
+    for c in s.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            _ => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+/// Writes the path data of the segments.
+fn write_path_data(out: &mut String, segments: &[Segment]) -> fmt::Result {
+    for (i, segment) in segments.iter().enumerate() {
+        if i != 0 {
+            out.push(' ');
+        }
+        match segment {
+            Segment::MoveTo(c) => write!(out, "M{},{}", c.x, c.y)?,
+            Segment::LineTo(c) => write!(out, "L{},{}", c.x, c.y)?,
+            Segment::QuadTo(c_1, end) => write!(out, "Q{},{} {},{}", c_1.x, c_1.y, end.x, end.y)?,
+            Segment::CubicTo(c_1, c_2, end) => write!(
+                out,
+                "C{},{} {},{} {},{}",
+                c_1.x, c_1.y, c_2.x, c_2.y, end.x, end.y
+            )?,
+            Segment::Close => out.push('Z'),
+        }
+    }
+    Ok(())
+}
+
+/// Writes the `matrix()` transformation which maps the rectangle `(0, 0)`-`size` onto
+/// the parallelogram with the corner `origin` and the two edges `u` and `v` from it.
+fn write_matrix_onto(
+    out: &mut String,
+    size: Coordinate<f64>,
+    origin: Coordinate<f64>,
+    u: Coordinate<f64>,
+    v: Coordinate<f64>,
+) -> fmt::Result {
+    let (width, height) = (size.x.max(1.), size.y.max(1.));
+    write!(
+        out,
+        "matrix({} {} {} {} {} {})",
+        u.x / width,
+        u.y / width,
+        v.x / height,
+        v.y / height,
+        origin.x,
+        origin.y
+    )
+}
+
+fn write_paint(out: &mut String, kind: &str, color: Color) -> fmt::Result {
+    let (r, g, b) = color.rgb();
+    write!(
+        out,
+        r#"{kind}="rgb({r},{g},{b})" {kind}-opacity="{a}""#,
+        kind = kind,
+        r = r,
+        g = g,
+        b = b,
+        a = f64::from(color.alpha()) / f64::from(u8::MAX)
+    )
+}
+
+fn write_path(
+    out: &mut String,
+    path: &RenderablePath,
+    images: &FxHashMap<ImageHash, ImageData>,
+    measure_text: &impl Fn(&Text) -> Coordinate<f64>,
+) -> fmt::Result {
+    let inner = path.get().get();
+    match &inner.shape {
+        Shape::Text(text) => {
+            let (origin, u, v) = match inner.parallelogram() {
+                Some(p) => p,
+                None => return Ok(()),
+            };
+            out.push_str(r#"<text transform=""#);
+            write_matrix_onto(out, measure_text(text), origin, u, v)?;
+            out.push_str("\" ");
+            write_paint(out, "fill", inner.color)?;
+            write!(
+                out,
+                r#" font-family="sans-serif" font-size="{}" dominant-baseline="text-before-edge" xml:space="preserve">"#,
+                text.font_size.get()
+            )?;
+            for (i, line) in text.lines().enumerate() {
+                write!(
+                    out,
+                    r#"<tspan x="0" y="{}">{}</tspan>"#,
+                    i as f64 * text.line_height(),
+                    escape(line)
+                )?;
+            }
+            out.push_str("</text>");
+        }
+        Shape::Image(hash) => {
+            let (data, (origin, u, v)) = match (images.get(hash), inner.parallelogram()) {
+                (Some(data), Some(p)) => (data, p),
+                _ => return Ok(()),
+            };
+            let unit = Coordinate { x: 1., y: 1. };
+            out.push_str(r#"<image width="1" height="1" preserveAspectRatio="none" transform=""#);
+            write_matrix_onto(out, unit, origin, u, v)?;
+            write!(out, r#"" href="{}"/>"#, data.url())?;
+        }
+        _ => {
+            out.push_str(r#"<path d=""#);
+            write_path_data(out, &inner.segments())?;
+            out.push_str(r#"" fill="none" "#);
+            write_paint(out, "stroke", inner.color)?;
+            write!(
+                out,
+                r#" stroke-width="{}" stroke-linecap="round" stroke-linejoin="round"/>"#,
+                inner.width.get()
+            )?;
+        }
+    }
+    out.push('\n');
+    Ok(())
+}
+
+/// Serializes the paths given from the bottom into an SVG document which fits them,
+/// or returns [`None`] if no paths given.
+///
+/// `measure_text` returns the size of a text when rendered without stretching.
+pub fn serialize<'a>(
+    paths: impl IntoIterator<Item = &'a RenderablePath>,
+    images: &FxHashMap<ImageHash, ImageData>,
+    measure_text: impl Fn(&Text) -> Coordinate<f64>,
+) -> Option<String> {
+    let paths = paths.into_iter().collect::<Vec<_>>();
+    let rect = (paths.iter())
+        .map(|path| *path.bounding_rect().get())
+        .reduce(utils::rect_union)?;
+    let mut out = String::new();
+    (|| {
+        writeln!(
+            out,
+            r#"<svg xmlns="http://www.w3.org/2000/svg" viewBox="{} {} {} {}" width="{}" height="{}">"#,
+            rect.min().x,
+            rect.min().y,
+            rect.width(),
+            rect.height(),
+            rect.width(),
+            rect.height()
+        )?;
+        for path in paths {
+            write_path(&mut out, path, images, &measure_text)?;
+        }
+        out.push_str("</svg>\n");
+        Ok::<_, fmt::Error>(())
+    })()
+    .expect("writing to a string never fails");
+    Some(out)
+}
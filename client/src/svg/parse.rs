@@ -0,0 +1,852 @@
+//! Conversion of SVG drawings made by other applications into paths.
+
+use crate::{
+    common::{Color, Path, Shape, Width},
+    utils::{self, Affine},
+};
+use anyhow::{anyhow, Result};
+use geo::{Coordinate, LineString};
+use roxmltree::Node;
+use std::f64::consts::TAU;
+
+/// A cursor over a list of numbers and commands, such as path data or `transform` attributes.
+struct Scanner<'a> {
+    s: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Scanner<'a> {
+    fn new(s: &'a str) -> Self {
+        Self {
+            s: s.as_bytes(),
+            pos: 0,
+        }
+    }
+
+    fn peek(&self) -> Option<u8> {
+        self.s.get(self.pos).copied()
+    }
+
+    fn is_end(&mut self) -> bool {
+        self.skip_separators();
+        self.pos == self.s.len()
+    }
+
+    fn skip_separators(&mut self) {
+        while matches!(self.peek(), Some(c) if c.is_ascii_whitespace() || c == b',') {
+            self.pos += 1;
+        }
+    }
+
+    fn skip_digits(&mut self) -> usize {
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_digit()) {
+            self.pos += 1;
+        }
+        self.pos - start
+    }
+
+    /// Consumes the given character after separators, if any.
+    fn eat(&mut self, c: u8) -> bool {
+        self.skip_separators();
+        let eaten = self.peek() == Some(c);
+        if eaten {
+            self.pos += 1;
+        }
+        eaten
+    }
+
+    /// Consumes a letter after separators, if any.
+    fn letter(&mut self) -> Option<u8> {
+        self.skip_separators();
+        let c = self.peek().filter(u8::is_ascii_alphabetic)?;
+        self.pos += 1;
+        Some(c)
+    }
+
+    /// Consumes a word after separators, if any.
+    fn word(&mut self) -> Option<&'a str> {
+        self.skip_separators();
+        let start = self.pos;
+        while matches!(self.peek(), Some(c) if c.is_ascii_alphabetic()) {
+            self.pos += 1;
+        }
+        if self.pos == start {
+            return None;
+        }
+        Some(std::str::from_utf8(&self.s[start..self.pos]).expect("an ASCII word"))
+    }
+
+    /// Consumes a number after separators, which may be followed by another one immediately
+    /// as in `1.5.5` or `1-2`.
+    fn number(&mut self) -> Option<f64> {
+        self.skip_separators();
+        let start = self.pos;
+        if matches!(self.peek(), Some(b'+' | b'-')) {
+            self.pos += 1;
+        }
+        let mut digits = self.skip_digits();
+        if self.peek() == Some(b'.') {
+            self.pos += 1;
+            digits += self.skip_digits();
+        }
+        if digits == 0 {
+            self.pos = start;
+            return None;
+        }
+        if matches!(self.peek(), Some(b'e' | b'E')) {
+            let mantissa_end = self.pos;
+            self.pos += 1;
+            if matches!(self.peek(), Some(b'+' | b'-')) {
+                self.pos += 1;
+            }
+            if self.skip_digits() == 0 {
+                self.pos = mantissa_end;
+            }
+        }
+        let number = std::str::from_utf8(&self.s[start..self.pos]).expect("an ASCII number");
+        number.parse().ok()
+    }
+
+    /// Consumes a flag of an elliptical arc, which may not be separated from the next argument.
+    fn flag(&mut self) -> Option<bool> {
+        self.skip_separators();
+        let flag = match self.peek()? {
+            b'0' => false,
+            b'1' => true,
+            _ => return None,
+        };
+        self.pos += 1;
+        Some(flag)
+    }
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum Curve {
+    Cubic,
+    Quadratic,
+}
+
+/// Flattens the subpaths of path data into polylines.
+struct PathBuilder {
+    /// The factor by which lengths are scaled onto the board.
+    scale: f64,
+    subpaths: Vec<Vec<Coordinate<f64>>>,
+    coords: Vec<Coordinate<f64>>,
+    current: Coordinate<f64>,
+    start: Coordinate<f64>,
+    /// The last control point, which is reflected by the shorthand curve commands.
+    ctrl: Option<(Curve, Coordinate<f64>)>,
+}
+
+impl PathBuilder {
+    /// The approximate length on the board of each segment of a flattened curve.
+    const SEGMENT_LENGTH: f64 = 4.;
+    const MAX_SEGMENTS: usize = 64;
+
+    fn new(scale: f64) -> Self {
+        Self {
+            scale,
+            subpaths: vec![],
+            coords: vec![],
+            current: Coordinate::zero(),
+            start: Coordinate::zero(),
+            ctrl: None,
+        }
+    }
+
+    /// Returns the number of segments into which a curve of about `len` is flattened.
+    fn segments(&self, len: f64) -> usize {
+        ((len * self.scale / Self::SEGMENT_LENGTH).ceil() as usize).clamp(1, Self::MAX_SEGMENTS)
+    }
+
+    fn push(&mut self, coord: Coordinate<f64>) {
+        if self.coords.is_empty() {
+            self.coords.push(self.current);
+        }
+        self.coords.push(coord);
+        self.current = coord;
+    }
+
+    fn flush(&mut self) {
+        if !self.coords.is_empty() {
+            self.subpaths.push(std::mem::take(&mut self.coords));
+        }
+    }
+
+    fn reflected_ctrl(&self, curve: Curve) -> Coordinate<f64> {
+        match self.ctrl {
+            Some((c, ctrl)) if c == curve => self.current * 2. - ctrl,
+            _ => self.current,
+        }
+    }
+
+    fn move_to(&mut self, coord: Coordinate<f64>) {
+        self.flush();
+        self.coords.push(coord);
+        self.current = coord;
+        self.start = coord;
+        self.ctrl = None;
+    }
+
+    fn line_to(&mut self, coord: Coordinate<f64>) {
+        self.push(coord);
+        self.ctrl = None;
+    }
+
+    fn cubic_to(&mut self, c_1: Coordinate<f64>, c_2: Coordinate<f64>, end: Coordinate<f64>) {
+        let start = self.current;
+        let n = self.segments(length(c_1 - start) + length(c_2 - c_1) + length(end - c_2));
+        for i in 1..n {
+            let t = i as f64 / n as f64;
+            let s = 1. - t;
+            self.push(
+                start * (s * s * s)
+                    + c_1 * (3. * s * s * t)
+                    + c_2 * (3. * s * t * t)
+                    + end * (t * t * t),
+            );
+        }
+        self.push(end);
+        self.ctrl = Some((Curve::Cubic, c_2));
+    }
+
+    fn quadratic_to(&mut self, c: Coordinate<f64>, end: Coordinate<f64>) {
+        let start = self.current;
+        let n = self.segments(length(c - start) + length(end - c));
+        for i in 1..n {
+            let t = i as f64 / n as f64;
+            let s = 1. - t;
+            self.push(start * (s * s) + c * (2. * s * t) + end * (t * t));
+        }
+        self.push(end);
+        self.ctrl = Some((Curve::Quadratic, c));
+    }
+
+    /// Flattens the elliptical arc, following the implementation notes of the SVG specification.
+    fn arc_to(
+        &mut self,
+        radii: Coordinate<f64>,
+        angle: f64,
+        large_arc: bool,
+        sweep: bool,
+        end: Coordinate<f64>,
+    ) {
+        let start = self.current;
+        let (mut rx, mut ry) = (radii.x.abs(), radii.y.abs());
+        if rx == 0. || ry == 0. || start == end {
+            return self.line_to(end);
+        }
+        let (sin, cos) = angle.to_radians().sin_cos();
+        let half = (start - end) / 2.;
+        let (x, y) = (cos * half.x + sin * half.y, -sin * half.x + cos * half.y);
+        let lambda = (x / rx).powi(2) + (y / ry).powi(2);
+        if lambda > 1. {
+            rx *= lambda.sqrt();
+            ry *= lambda.sqrt();
+        }
+        let (rx2, ry2) = (rx * rx, ry * ry);
+        let numer = (rx2 * ry2 - rx2 * y * y - ry2 * x * x).max(0.);
+        let mut k = (numer / (rx2 * y * y + ry2 * x * x)).sqrt();
+        if large_arc == sweep {
+            k = -k;
+        }
+        let (cx, cy) = (k * rx * y / ry, -k * ry * x / rx);
+        let mid = (start + end) / 2.;
+        let center = Coordinate {
+            x: cos * cx - sin * cy + mid.x,
+            y: sin * cx + cos * cy + mid.y,
+        };
+        let theta = ((y - cy) / ry).atan2((x - cx) / rx);
+        let mut delta = ((-y - cy) / ry).atan2((-x - cx) / rx) - theta;
+        if sweep && delta < 0. {
+            delta += TAU;
+        } else if !sweep && delta > 0. {
+            delta -= TAU;
+        }
+        let n = self.segments(delta.abs() * rx.max(ry));
+        for i in 1..n {
+            let (s, c) = (theta + delta * i as f64 / n as f64).sin_cos();
+            let (x, y) = (rx * c, ry * s);
+            self.push(
+                center
+                    + Coordinate {
+                        x: cos * x - sin * y,
+                        y: sin * x + cos * y,
+                    },
+            );
+        }
+        self.push(end);
+        self.ctrl = None;
+    }
+
+    fn close(&mut self) {
+        if !self.coords.is_empty() {
+            self.push(self.start);
+        }
+        self.flush();
+        self.current = self.start;
+        self.ctrl = None;
+    }
+
+    fn finish(mut self) -> Vec<Vec<Coordinate<f64>>> {
+        self.flush();
+        self.subpaths
+    }
+}
+
+fn length(v: Coordinate<f64>) -> f64 {
+    v.x.hypot(v.y)
+}
+
+/// Applies one command of path data with its arguments, or returns [`None`] if malformed.
+fn apply_path_command(s: &mut Scanner<'_>, b: &mut PathBuilder, command: u8) -> Option<()> {
+    let base = if command.is_ascii_lowercase() {
+        b.current
+    } else {
+        Coordinate::zero()
+    };
+    let coord = |s: &mut Scanner<'_>| {
+        let x = s.number()?;
+        let y = s.number()?;
+        Some(base + Coordinate { x, y })
+    };
+    match command.to_ascii_uppercase() {
+        b'M' => b.move_to(coord(s)?),
+        b'L' => b.line_to(coord(s)?),
+        b'H' => b.line_to(Coordinate {
+            x: base.x + s.number()?,
+            y: b.current.y,
+        }),
+        b'V' => b.line_to(Coordinate {
+            x: b.current.x,
+            y: base.y + s.number()?,
+        }),
+        b'C' => {
+            let (c_1, c_2, end) = (coord(s)?, coord(s)?, coord(s)?);
+            b.cubic_to(c_1, c_2, end);
+        }
+        b'S' => {
+            let c_1 = b.reflected_ctrl(Curve::Cubic);
+            let (c_2, end) = (coord(s)?, coord(s)?);
+            b.cubic_to(c_1, c_2, end);
+        }
+        b'Q' => {
+            let (c, end) = (coord(s)?, coord(s)?);
+            b.quadratic_to(c, end);
+        }
+        b'T' => {
+            let c = b.reflected_ctrl(Curve::Quadratic);
+            b.quadratic_to(c, coord(s)?);
+        }
+        b'A' => {
+            let radii = Coordinate {
+                x: s.number()?,
+                y: s.number()?,
+            };
+            let angle = s.number()?;
+            let (large_arc, sweep) = (s.flag()?, s.flag()?);
+            b.arc_to(radii, angle, large_arc, sweep, coord(s)?);
+        }
+        b'Z' => b.close(),
+        _ => return None,
+    }
+    Some(())
+}
+
+/// Flattens the path data into polylines, up to the first error as browsers render it.
+fn parse_path_data(data: &str, scale: f64) -> Vec<Vec<Coordinate<f64>>> {
+    let mut s = Scanner::new(data);
+    let mut b = PathBuilder::new(scale);
+    let mut prev = None;
+    while !s.is_end() {
+        // a command letter may be omitted when repeated, except that `M` repeats as `L`
+        let command = match (s.letter(), prev) {
+            (Some(command), _) => command,
+            (None, Some(prev)) if !matches!(prev, b'Z' | b'z') => prev,
+            _ => break,
+        };
+        if apply_path_command(&mut s, &mut b, command).is_none() {
+            break;
+        }
+        prev = Some(match command {
+            b'M' => b'L',
+            b'm' => b'l',
+            command => command,
+        });
+    }
+    b.finish()
+}
+
+fn parse_numbers(s: &str) -> Vec<f64> {
+    let mut s = Scanner::new(s);
+    std::iter::from_fn(|| s.number()).collect()
+}
+
+/// Parses a `transform` attribute, or returns [`None`] if malformed.
+fn parse_transform(s: &str) -> Option<Affine> {
+    let mut s = Scanner::new(s);
+    let mut transform = Affine::IDENTITY;
+    while !s.is_end() {
+        let name = s.word()?;
+        if !s.eat(b'(') {
+            return None;
+        }
+        let args = std::iter::from_fn(|| s.number()).collect::<Vec<_>>();
+        if !s.eat(b')') {
+            return None;
+        }
+        let t = match (name, args.as_slice()) {
+            ("matrix", &[a, b, c, d, e, f]) => Affine::new([[a, c, e], [b, d, f]]),
+            ("translate", &[x]) => Affine::translation(Coordinate { x, y: 0. }),
+            ("translate", &[x, y]) => Affine::translation(Coordinate { x, y }),
+            ("scale", &[s]) => Affine::scaling(s, s),
+            ("scale", &[x, y]) => Affine::scaling(x, y),
+            ("rotate", &[a]) => Affine::rotation(a.to_radians()),
+            ("rotate", &[a, x, y]) => Affine::rotation(a.to_radians()).about(Coordinate { x, y }),
+            ("skewX", &[a]) => Affine::new([[1., a.to_radians().tan(), 0.], [0., 1., 0.]]),
+            ("skewY", &[a]) => Affine::new([[1., 0., 0.], [a.to_radians().tan(), 1., 0.]]),
+            _ => return None,
+        };
+        // the rightmost transformation applies first
+        transform = t.then(transform);
+    }
+    Some(transform)
+}
+
+/// Parses a length in pixels, or returns [`None`] if it is relative or malformed.
+fn parse_length(s: &str) -> Option<f64> {
+    let s = s.trim();
+    let unit_start = s
+        .find(|c: char| c.is_ascii_alphabetic() || c == '%')
+        .unwrap_or(s.len());
+    let value = s[..unit_start].parse::<f64>().ok()?;
+    let px_per_unit = match &s[unit_start..] {
+        "" | "px" => 1.,
+        "in" => 96.,
+        "cm" => 96. / 2.54,
+        "mm" => 96. / 25.4,
+        "pt" => 96. / 72.,
+        "pc" => 16.,
+        _ => return None,
+    };
+    Some(value * px_per_unit)
+}
+
+/// Parses `#rgb`, `#rrggbb`, `rgb(r, g, b)` or a basic color keyword.
+fn parse_color(s: &str) -> Option<Color> {
+    let s = s.trim();
+    if let Some(hex) = s.strip_prefix('#').filter(|hex| hex.is_ascii()) {
+        let channel = |i: usize, len: usize| {
+            u8::from_str_radix(hex.get(i * len..(i + 1) * len)?, 16)
+                .ok()
+                .map(|c| if len == 1 { c * 0x11 } else { c })
+        };
+        let len = match hex.len() {
+            3 => 1,
+            6 => 2,
+            _ => return None,
+        };
+        return Some(Color::opaque(
+            channel(0, len)?,
+            channel(1, len)?,
+            channel(2, len)?,
+        ));
+    }
+    if let Some(args) = (s.strip_prefix("rgb("))
+        .or_else(|| s.strip_prefix("rgba("))
+        .and_then(|s| s.strip_suffix(')'))
+    {
+        let channel = |arg: &str| {
+            let arg = arg.trim();
+            let value = match arg.strip_suffix('%') {
+                Some(percent) => percent.parse::<f64>().ok()? * 255. / 100.,
+                None => arg.parse::<f64>().ok()?,
+            };
+            Some(value.round().clamp(0., 255.) as u8)
+        };
+        let mut args = args.split(',');
+        let (r, g, b) = (args.next()?, args.next()?, args.next()?);
+        return Some(Color::opaque(channel(r)?, channel(g)?, channel(b)?));
+    }
+    let color = match s.to_ascii_lowercase().as_str() {
+        "black" => Color::opaque(0, 0, 0),
+        "silver" => Color::opaque(192, 192, 192),
+        "gray" | "grey" => Color::opaque(128, 128, 128),
+        "white" => Color::opaque(255, 255, 255),
+        "maroon" => Color::opaque(128, 0, 0),
+        "red" => Color::opaque(255, 0, 0),
+        "purple" => Color::opaque(128, 0, 128),
+        "fuchsia" | "magenta" => Color::opaque(255, 0, 255),
+        "green" => Color::opaque(0, 128, 0),
+        "lime" => Color::opaque(0, 255, 0),
+        "olive" => Color::opaque(128, 128, 0),
+        "yellow" => Color::opaque(255, 255, 0),
+        "orange" => Color::opaque(255, 165, 0),
+        "navy" => Color::opaque(0, 0, 128),
+        "blue" => Color::opaque(0, 0, 255),
+        "teal" => Color::opaque(0, 128, 128),
+        "aqua" | "cyan" => Color::opaque(0, 255, 255),
+        _ => return None,
+    };
+    Some(color)
+}
+
+/// Parses a paint, where `Some(None)` means `none`, or returns [`None`] if it is inherited.
+fn parse_paint(s: &str) -> Option<Option<Color>> {
+    match s.trim() {
+        "none" => Some(None),
+        "inherit" => None,
+        // gradients and patterns are approximated by black, as the current color is
+        s if s.starts_with("url(") || s == "currentColor" => Some(Some(Color::BLACK)),
+        s => parse_color(s).map(Some),
+    }
+}
+
+/// Returns the presentation property of the element, where its `style` attribute has priority.
+fn property<'a>(node: Node<'a, '_>, name: &str) -> Option<&'a str> {
+    let from_style = (node.attribute("style").into_iter())
+        .flat_map(|style| style.split(';'))
+        .filter_map(|decl| decl.split_once(':'))
+        .find(|(n, _)| n.trim() == name)
+        .map(|(_, value)| value.trim());
+    from_style.or_else(|| node.attribute(name))
+}
+
+/// The state inherited from the ancestors of an element.
+struct Context {
+    transform: Affine,
+    stroke: Option<Color>,
+    fill: Option<Color>,
+    stroke_width: f64,
+}
+
+impl Context {
+    /// Returns the transformation from the user space of the root element onto the board,
+    /// which scales its `viewBox` to its `width` and `height`.
+    fn root_transform(root: Node<'_, '_>) -> Affine {
+        let view_box = match root.attribute("viewBox").map(parse_numbers).as_deref() {
+            Some(&[x, y, width, height]) if width > 0. && height > 0. => {
+                Some((Coordinate { x, y }, width, height))
+            }
+            _ => None,
+        };
+        let (origin, vb_width, vb_height) = match view_box {
+            Some(view_box) => view_box,
+            None => return Affine::IDENTITY,
+        };
+        let scale_x = root
+            .attribute("width")
+            .and_then(parse_length)
+            .map(|w| w / vb_width);
+        let scale_y = (root.attribute("height"))
+            .and_then(parse_length)
+            .map(|h| h / vb_height);
+        let scale = match (scale_x, scale_y) {
+            (Some(x), Some(y)) => x.min(y),
+            (Some(s), None) | (None, Some(s)) => s,
+            (None, None) => 1.,
+        };
+        Affine::translation(-origin).then(Affine::scaling(scale, scale))
+    }
+
+    /// Returns the context of the element, or [`None`] if it is not rendered directly.
+    fn of(node: Node<'_, '_>) -> Option<Self> {
+        let mut context = Self {
+            transform: Affine::IDENTITY,
+            stroke: None,
+            fill: Some(Color::BLACK),
+            stroke_width: 1.,
+        };
+        let mut ancestors = node
+            .ancestors()
+            .filter(Node::is_element)
+            .collect::<Vec<_>>();
+        ancestors.reverse();
+        if let Some(&root) = ancestors.first() {
+            context.transform = Self::root_transform(root);
+        }
+        for node in ancestors {
+            if matches!(
+                node.tag_name().name(),
+                "defs" | "symbol" | "marker" | "pattern" | "clipPath" | "mask"
+            ) || property(node, "display") == Some("none")
+            {
+                return None;
+            }
+            if let Some(transform) = node.attribute("transform").and_then(parse_transform) {
+                context.transform = transform.then(context.transform);
+            }
+            if let Some(stroke) = property(node, "stroke").and_then(parse_paint) {
+                context.stroke = stroke;
+            }
+            if let Some(fill) = property(node, "fill").and_then(parse_paint) {
+                context.fill = fill;
+            }
+            if let Some(width) = property(node, "stroke-width").and_then(parse_length) {
+                context.stroke_width = width;
+            }
+        }
+        Some(context)
+    }
+
+    /// Returns the color and the width of the outlines, or [`None`] if they are invisible.
+    ///
+    /// A filled shape without strokes is outlined by its fill color.
+    fn pen(&self) -> Option<(Color, Width)> {
+        let (color, width) = match (self.stroke, self.fill) {
+            (Some(stroke), _) => (stroke, self.stroke_width * self.transform.mean_scale()),
+            (None, Some(fill)) => (fill, f64::from(Width::MIN.get())),
+            (None, None) => return None,
+        };
+        Some((color, Width::new(width.round() as _)))
+    }
+}
+
+/// Returns the closed polyline of four corners of the rectangle.
+fn corners(min: Coordinate<f64>, size: Coordinate<f64>) -> Vec<Coordinate<f64>> {
+    let max = min + size;
+    vec![
+        min,
+        Coordinate { x: max.x, y: min.y },
+        max,
+        Coordinate { x: min.x, y: max.y },
+        min,
+    ]
+}
+
+/// Returns the shapes of the supported element with their coordinates in its user space.
+fn shapes_of(node: Node<'_, '_>, scale: f64) -> Vec<(Shape, Vec<Coordinate<f64>>)> {
+    let number = |name: &str| node.attribute(name).and_then(parse_length).unwrap_or(0.);
+    let coord = |x: &str, y: &str| Coordinate {
+        x: number(x),
+        y: number(y),
+    };
+    match node.tag_name().name() {
+        "path" => {
+            let data = node.attribute("d").unwrap_or_default();
+            (parse_path_data(data, scale).into_iter())
+                .map(|coords| (Shape::Line, coords))
+                .collect()
+        }
+        "polyline" => {
+            let numbers = parse_numbers(node.attribute("points").unwrap_or_default());
+            let coords = (numbers.chunks_exact(2))
+                .map(|c| Coordinate { x: c[0], y: c[1] })
+                .collect();
+            vec![(Shape::Line, coords)]
+        }
+        "line" => vec![(Shape::Line, vec![coord("x1", "y1"), coord("x2", "y2")])],
+        "rect" => {
+            let size = coord("width", "height");
+            if size.x <= 0. || size.y <= 0. {
+                return vec![];
+            }
+            vec![(Shape::Rectangle, corners(coord("x", "y"), size))]
+        }
+        "circle" => {
+            let r = number("r");
+            if r <= 0. {
+                return vec![];
+            }
+            let radius = Coordinate { x: r, y: r };
+            let min = coord("cx", "cy") - radius;
+            vec![(Shape::Ellipse, corners(min, radius * 2.))]
+        }
+        _ => unreachable!(),
+    }
+}
+
+/// Converts the `<path>`, `<polyline>`, `<line>`, `<rect>` and `<circle>` elements
+/// of the SVG document into paths from the bottom, with curves flattened.
+///
+/// Their colors are those of the strokes (or the fills if not stroked) as they are.
+pub fn parse(source: &str) -> Result<Vec<Path>> {
+    let doc = roxmltree::Document::parse(source)?;
+    if doc.root_element().tag_name().name() != "svg" {
+        return Err(anyhow!("not an SVG document"));
+    }
+    let mut paths = vec![];
+    let nodes = doc.descendants().filter(|node| {
+        node.is_element()
+            && matches!(
+                node.tag_name().name(),
+                "path" | "polyline" | "line" | "rect" | "circle"
+            )
+    });
+    for node in nodes {
+        let context = match Context::of(node) {
+            Some(context) => context,
+            None => continue,
+        };
+        let (color, width) = match context.pen() {
+            Some(pen) => pen,
+            None => continue,
+        };
+        let shapes = shapes_of(node, context.transform.mean_scale());
+        for (shape, coords) in shapes {
+            let mut coords = (coords.into_iter())
+                .map(|c| utils::coord_map_scalars(context.transform.apply(c), |s| s.round() as _))
+                .collect::<Vec<Coordinate<i32>>>();
+            if let Shape::Line = shape {
+                coords.dedup();
+                if coords.len() < 2 {
+                    continue;
+                }
+            }
+            paths.push(Path {
+                color,
+                width,
+                shape,
+                coords: LineString(coords),
+            });
+        }
+    }
+    Ok(paths)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Flattens the path data into polylines with their coordinates rounded.
+    fn polylines(data: &str) -> Vec<Vec<(i32, i32)>> {
+        (parse_path_data(data, 1.).into_iter())
+            .map(|coords| {
+                (coords.into_iter())
+                    .map(|c| (c.x.round() as _, c.y.round() as _))
+                    .collect()
+            })
+            .collect()
+    }
+
+    fn assert_near(a: Coordinate<f64>, b: Coordinate<f64>) {
+        assert!(length(a - b) < 1e-9, "{:?} != {:?}", a, b);
+    }
+
+    #[test]
+    fn repeats_omitted_commands() {
+        assert_eq!(
+            polylines("M0 0 10 0 10 10 L20 10 30 10"),
+            [[(0, 0), (10, 0), (10, 10), (20, 10), (30, 10)]],
+        );
+        assert_eq!(polylines("m1 1 2 2-1 1"), [[(1, 1), (3, 3), (2, 4)]]);
+        assert_eq!(
+            polylines("M0 0h10 10v5"),
+            [[(0, 0), (10, 0), (20, 0), (20, 5)]]
+        );
+    }
+
+    #[test]
+    fn continues_relative_commands_from_start_after_close() {
+        assert_eq!(
+            polylines("m10 10 l10 0 0 10 z l5 5 z m1 1 l1 0"),
+            [
+                vec![(10, 10), (20, 10), (20, 20), (10, 10)],
+                vec![(10, 10), (15, 15), (10, 10)],
+                vec![(11, 11), (12, 11)],
+            ],
+        );
+        // a number cannot follow `Z` without a command
+        assert_eq!(polylines("M0 0 L1 0 Z 2 2"), [[(0, 0), (1, 0), (0, 0)]]);
+    }
+
+    #[test]
+    fn parses_arc_flags_without_separators() {
+        // the center of the arc from (0, 0) to (1, 1) is (0, 1) if swept, or (1, 0) otherwise
+        for (data, center) in [
+            ("M0 0a1 1 0 00 1 1", Coordinate { x: 1., y: 0. }),
+            ("M0 0a1 1 0 001 1", Coordinate { x: 1., y: 0. }),
+            ("M0 0a1 1 0 011 1", Coordinate { x: 0., y: 1. }),
+            ("M0 0a1,1,0,0,1,1,1", Coordinate { x: 0., y: 1. }),
+        ] {
+            let subpaths = parse_path_data(data, 100.);
+            assert_eq!(subpaths.len(), 1, "{}", data);
+            let coords = &subpaths[0];
+            assert!(coords.len() > 2, "{}", data);
+            assert_near(coords[0], Coordinate::zero());
+            assert_near(*coords.last().unwrap(), Coordinate { x: 1., y: 1. });
+            for &c in coords {
+                assert!((length(c - center) - 1.).abs() < 1e-9, "{}: {:?}", data, c);
+            }
+        }
+    }
+
+    #[test]
+    fn reflects_control_points() {
+        assert_eq!(
+            parse_path_data("M0 0 C0 10 10 10 10 0 S20 -10 20 0", 1.),
+            parse_path_data("M0 0 C0 10 10 10 10 0 C10 -10 20 -10 20 0", 1.),
+        );
+        assert_eq!(
+            parse_path_data("M0 0 c0 10 10 10 10 0 s10 -10 10 0", 1.),
+            parse_path_data("M0 0 C0 10 10 10 10 0 C10 -10 20 -10 20 0", 1.),
+        );
+        assert_eq!(
+            parse_path_data("M0 0 Q5 10 10 0 T20 0 30 0", 1.),
+            parse_path_data("M0 0 Q5 10 10 0 Q15 -10 20 0 Q25 10 30 0", 1.),
+        );
+        // the current point is the control point unless the previous command is of the kind
+        assert_eq!(
+            parse_path_data("M0 0 L10 0 S20 10 20 0", 1.),
+            parse_path_data("M0 0 L10 0 C10 0 20 10 20 0", 1.),
+        );
+        assert_eq!(
+            parse_path_data("M0 0 C0 10 10 10 10 0 T20 0", 1.),
+            parse_path_data("M0 0 C0 10 10 10 10 0 Q10 0 20 0", 1.),
+        );
+    }
+
+    #[test]
+    fn composes_transforms_from_right() {
+        let t = parse_transform("translate(10 0) scale(2)").unwrap();
+        assert_near(
+            t.apply(Coordinate { x: 1., y: 1. }),
+            Coordinate { x: 12., y: 2. },
+        );
+        let t = parse_transform("rotate(90),translate(10,0)").unwrap();
+        assert_near(t.apply(Coordinate::zero()), Coordinate { x: 0., y: 10. });
+        let t = parse_transform("matrix(1 0 0 1 5 6) rotate(180 1 1)").unwrap();
+        assert_near(t.apply(Coordinate::zero()), Coordinate { x: 7., y: 8. });
+        assert_eq!(parse_transform("scale(2"), None);
+        assert_eq!(parse_transform("skew(2)"), None);
+
+        // the transformations of the ancestors apply after that of the element
+        let paths = parse(
+            r#"<svg xmlns="http://www.w3.org/2000/svg">
+                <g transform="translate(100 0)">
+                    <path d="M0 0 L10 0" transform="scale(2)" stroke="red"/>
+                </g>
+            </svg>"#,
+        )
+        .unwrap();
+        assert_eq!(paths.len(), 1);
+        assert_eq!(paths[0].coords, vec![(100, 0), (120, 0)].into());
+        assert_eq!(paths[0].color, Color::opaque(255, 0, 0));
+    }
+
+    #[test]
+    fn parses_colors() {
+        let orange = Some(Color::opaque(255, 136, 0));
+        assert_eq!(parse_color("#f80"), orange);
+        assert_eq!(parse_color("#FF8800"), orange);
+        assert_eq!(parse_color(" rgb(255, 136, 0) "), orange);
+        assert_eq!(parse_color("rgba(255,136,0,0.5)"), orange);
+        assert_eq!(
+            parse_color("rgb(100%, 0%, 50%)"),
+            Some(Color::opaque(255, 0, 128))
+        );
+        assert_eq!(
+            parse_color("rgb(300, -5, 0)"),
+            Some(Color::opaque(255, 0, 0))
+        );
+        assert_eq!(parse_color("Orange"), Some(Color::opaque(255, 165, 0)));
+        assert_eq!(parse_color("grey"), Some(Color::opaque(128, 128, 128)));
+        for invalid in ["#ff88", "#ggg", "rgb(1, 2)", "bluish", ""] {
+            assert_eq!(parse_color(invalid), None, "{}", invalid);
+        }
+        assert_eq!(parse_paint("none"), Some(None));
+        assert_eq!(parse_paint("inherit"), None);
+        assert_eq!(parse_paint("blue"), Some(Some(Color::opaque(0, 0, 255))));
+    }
+}
@@ -1,6 +1,9 @@
 use arrayvec::ArrayVec;
-use geo::{map_coords::MapCoordsInplace as _, prelude::*, CoordNum, Coordinate, Rect};
-use std::cmp::Ordering;
+use geo::{
+    map_coords::MapCoordsInplace as _, prelude::*, CoordNum, Coordinate, Line, LineString, Point,
+    Rect,
+};
+use std::{cmp::Ordering, mem};
 
 pub fn coord_map_scalars<T, U>(coord: Coordinate<T>, mut f: impl FnMut(T) -> U) -> Coordinate<U>
 where
@@ -38,6 +41,20 @@ where
     Rect::new(rect.min() - delta, rect.max() + delta)
 }
 
+/// Returns the smallest rectangle which contains both rectangles.
+pub fn rect_union(rect_1: Rect<i32>, rect_2: Rect<i32>) -> Rect<i32> {
+    Rect::new(
+        Coordinate {
+            x: rect_1.min().x.min(rect_2.min().x),
+            y: rect_1.min().y.min(rect_2.min().y),
+        },
+        Coordinate {
+            x: rect_1.max().x.max(rect_2.max().x),
+            y: rect_1.max().y.max(rect_2.max().y),
+        },
+    )
+}
+
 #[derive(Debug)]
 pub struct RectDiff {
     pub removed: ArrayVec<Rect<i32>, 2>,
@@ -128,3 +145,188 @@ pub fn rect_diff(
 
     RectDiff { removed, added }
 }
+
+/// Returns the parameter interval `[t₀, t₁]` of the points `seg.start + (seg.end - seg.start) t`
+/// (0 ≤ _t_ ≤ 1) within `radius` from `line`, or [`None`] if it is empty.
+fn interval_within(seg: Line<f64>, line: Line<f64>, radius: f64) -> Option<(f64, f64)> {
+    const ITERATIONS: usize = 48;
+    let at = |t: f64| seg.start + seg.delta() * t;
+    // the distance is a convex function of `t`, so the interval is found by searches
+    let dist = |t: f64| Point::from(at(t)).euclidean_distance(&line);
+    let (mut lo, mut hi) = (0., 1.);
+    for _ in 0..ITERATIONS {
+        let (m_1, m_2) = ((lo * 2. + hi) / 3., (lo + hi * 2.) / 3.);
+        if dist(m_1) < dist(m_2) {
+            hi = m_2;
+        } else {
+            lo = m_1;
+        }
+    }
+    let t_min = (lo + hi) / 2.;
+    if dist(t_min) > radius {
+        return None;
+    }
+    let search = |mut outside: f64, mut inside: f64| {
+        if dist(outside) <= radius {
+            return outside;
+        }
+        for _ in 0..ITERATIONS {
+            let mid = (outside + inside) / 2.;
+            if dist(mid) <= radius {
+                inside = mid;
+            } else {
+                outside = mid;
+            }
+        }
+        inside
+    };
+    Some((search(0., t_min), search(1., t_min)))
+}
+
+/// Splits the polyline into the pieces outside the region within `radius` from `line`,
+/// or returns [`None`] if the polyline does not meet the region.
+pub fn cut_line_string(
+    coords: &LineString<i32>,
+    line: Line<f64>,
+    radius: f64,
+) -> Option<Vec<LineString<i32>>> {
+    let region = expand_rect(line.bounding_rect(), radius);
+    let mut touched = false;
+    let mut pieces = vec![];
+    let mut piece = vec![];
+    for seg in coords.lines() {
+        let seg = Line::new(
+            coord_map_scalars(seg.start, f64::from),
+            coord_map_scalars(seg.end, f64::from),
+        );
+        let interval = if seg.bounding_rect().intersects(&region) {
+            interval_within(seg, line, radius)
+        } else {
+            None
+        };
+        if piece.is_empty() {
+            piece.push(seg.start);
+        }
+        match interval {
+            None => piece.push(seg.end),
+            Some((t_0, t_1)) => {
+                touched = true;
+                if t_0 > 0. {
+                    piece.push(seg.start + seg.delta() * t_0);
+                }
+                pieces.push(mem::take(&mut piece));
+                if t_1 < 1. {
+                    piece.push(seg.start + seg.delta() * t_1);
+                    piece.push(seg.end);
+                }
+            }
+        }
+    }
+    if !touched {
+        return None;
+    }
+    pieces.push(piece);
+    let pieces = (pieces.into_iter())
+        .map(|piece| {
+            let mut piece = (piece.into_iter())
+                .map(|c| coord_map_scalars(c, |s| s.round() as i32))
+                .collect::<Vec<_>>();
+            piece.dedup();
+            LineString(piece)
+        })
+        .filter(|piece| piece.0.len() >= 2)
+        .collect();
+    Some(pieces)
+}
+
+/// Returns the corners of the rectangle in clockwise order from the top-left one.
+pub fn rect_corners<T>(rect: Rect<T>) -> [Coordinate<T>; 4]
+where
+    T: CoordNum,
+{
+    let (min, max) = (rect.min(), rect.max());
+    [
+        min,
+        Coordinate { x: max.x, y: min.y },
+        max,
+        Coordinate { x: min.x, y: max.y },
+    ]
+}
+
+/// An affine transformation `(x, y) ↦ (m₀₀ x + m₀₁ y + m₀₂, m₁₀ x + m₁₁ y + m₁₂)`.
+#[derive(Clone, Copy, PartialEq, Debug)]
+pub struct Affine([[f64; 3]; 2]);
+
+impl Affine {
+    pub const IDENTITY: Self = Self([[1., 0., 0.], [0., 1., 0.]]);
+
+    pub fn new(matrix: [[f64; 3]; 2]) -> Self {
+        Self(matrix)
+    }
+
+    pub fn scaling(x: f64, y: f64) -> Self {
+        Self([[x, 0., 0.], [0., y, 0.]])
+    }
+
+    pub fn rotation(angle: f64) -> Self {
+        let (sin, cos) = angle.sin_cos();
+        Self([[cos, -sin, 0.], [sin, cos, 0.]])
+    }
+
+    pub fn translation(delta: Coordinate<f64>) -> Self {
+        Self([[1., 0., delta.x], [0., 1., delta.y]])
+    }
+
+    /// Returns the transformation which applies `self` and then `other`.
+    pub fn then(self, other: Self) -> Self {
+        let [[a, b, c], [d, e, f]] = other.0;
+        let [[p, q, r], [s, t, u]] = self.0;
+        Self([
+            [a * p + b * s, a * q + b * t, a * r + b * u + c],
+            [d * p + e * s, d * q + e * t, d * r + e * u + f],
+        ])
+    }
+
+    /// Returns the transformation which is `self` with `center` as the origin.
+    pub fn about(self, center: Coordinate<f64>) -> Self {
+        Self::translation(-center)
+            .then(self)
+            .then(Self::translation(center))
+    }
+
+    /// Returns the inverse transformation, or [`None`] if it is singular.
+    pub fn inverse(self) -> Option<Self> {
+        let [[a, b, c], [d, e, f]] = self.0;
+        let det = a * e - b * d;
+        if det == 0. {
+            return None;
+        }
+        let (a, b, d, e) = (e / det, -b / det, -d / det, a / det);
+        Some(Self([[a, b, -(a * c + b * f)], [d, e, -(d * c + e * f)]]))
+    }
+
+    /// Returns the factor by which the transformation scales lengths on average.
+    pub fn mean_scale(self) -> f64 {
+        let [[a, b, _], [d, e, _]] = self.0;
+        (a * e - b * d).abs().sqrt()
+    }
+
+    pub fn apply(self, coord: Coordinate<f64>) -> Coordinate<f64> {
+        let [[a, b, c], [d, e, f]] = self.0;
+        Coordinate {
+            x: a * coord.x + b * coord.y + c,
+            y: d * coord.x + e * coord.y + f,
+        }
+    }
+
+    /// Applies the transformation to the coordinates, rounding the results.
+    pub fn apply_to_line_string(self, coords: &LineString<i32>) -> LineString<i32> {
+        (coords.0.iter())
+            .map(|&c| {
+                coord_map_scalars(self.apply(coord_map_scalars(c, f64::from)), |s| {
+                    s.round() as _
+                })
+            })
+            .collect()
+    }
+}
@@ -1,12 +1,18 @@
 //! A view, which renders objects and notifies the controller of recieved user events.
 
 use crate::{
-    common::{Color, OnScreen, RenderablePath, Tool},
+    common::{
+        BoardId, Boards, Clip, Color, DocLayerId, DocLayers, EraserRadius, FontSize, ImageData,
+        ImageHash, LassoMode, OnScreen, Palette, RenderablePath, Scale, Shape, Text, Tool, Width,
+    },
     ctrl::Controller,
-    web,
+    document::Document,
+    model::{ExportScope, Restack},
+    raster, svg, utils, web,
 };
 use enum_map::{enum_map, Enum, EnumMap};
-use geo::{Coordinate, Rect};
+use geo::{Coordinate, LineString, Rect};
+use rustc_hash::FxHashMap;
 use std::{cell::RefCell, rc::Rc};
 use wasm_bindgen::prelude::*;
 
@@ -30,14 +36,21 @@ pub enum Layer {
     Temp,
 }
 
+/// Decoded images shared among the layers.
+type ImageCache = Rc<RefCell<FxHashMap<ImageHash, web_sys::HtmlImageElement>>>;
+
+/// The font to rasterize texts with, fetched on the first export.
+type FontCache = Rc<RefCell<Option<Rc<[u8]>>>>;
+
 #[derive(Clone, Debug)]
 pub struct LayerHandle {
     canvas: web::Canvas,
+    images: ImageCache,
 }
 
 impl LayerHandle {
-    fn new(canvas: web::Canvas) -> Self {
-        Self { canvas }
+    fn new(canvas: web::Canvas, images: ImageCache) -> Self {
+        Self { canvas, images }
     }
 
     pub fn translate(&self, delta: Coordinate<i32>) {
@@ -45,7 +58,29 @@ impl LayerHandle {
     }
 
     pub fn render_path(&self, path: &RenderablePath) {
-        self.set_style_for_path();
+        match &path.get().get().shape {
+            Shape::Text(text) => {
+                if let Some((origin, u, v)) = path.get().get().parallelogram() {
+                    self.canvas.set_fill_color(path.get().get().color);
+                    self.canvas.fill_text(text, origin, u, v);
+                }
+                return;
+            }
+            Shape::Image(hash) => {
+                // an image not loaded yet will be rendered on the next rerendering
+                let images = self.images.borrow();
+                if let (Some(image), Some((origin, u, v))) =
+                    (images.get(hash), path.get().get().parallelogram())
+                {
+                    if image.complete() && image.natural_width() != 0 {
+                        self.canvas.draw_image(image, origin, u, v);
+                    }
+                }
+                return;
+            }
+            _ => {}
+        }
+        self.set_style_for_path(path.get().get().width);
         self.canvas.set_stroke_color(path.get().get().color);
         self.canvas.stroke_path_obj(path.get().path_obj());
     }
@@ -55,18 +90,60 @@ impl LayerHandle {
         self.canvas.stroke_path_obj(path.bounding_rect().path_obj());
     }
 
+    pub fn render_lock_indicator_of(&self, path: &RenderablePath) {
+        self.set_style_for_lock_indicator();
+        self.canvas.stroke_path_obj(path.bounding_rect().path_obj());
+    }
+
     pub fn render_curve(
         &self,
         color: Color,
+        width: Width,
         start: Coordinate<i32>,
         control: Coordinate<i32>,
         end: Coordinate<i32>,
     ) {
-        self.set_style_for_path();
+        self.set_style_for_path(width);
         self.canvas.set_stroke_color(color);
         self.canvas.stroke_curve(start, control, end);
     }
 
+    pub fn render_eraser(&self, center: Coordinate<i32>, radius: EraserRadius) {
+        let center = utils::coord_map_scalars(center, f64::from);
+        let radius = f64::from(radius.get());
+        let circle = web::Path::ellipse(
+            center,
+            Coordinate { x: radius, y: 0. },
+            Coordinate { x: 0., y: radius },
+        );
+        self.set_style_for_bounding_rect();
+        self.canvas.stroke_path_obj(&circle);
+    }
+
+    pub fn render_lasso(&self, coords: &[Coordinate<i32>]) {
+        let lasso = web::Path::polyline(&LineString::from(coords.to_vec()));
+        self.set_style_for_selection_rect();
+        self.canvas.fill_path_obj(&lasso);
+        self.set_style_for_bounding_rect();
+        self.canvas.stroke_path_obj(&lasso);
+    }
+
+    pub fn render_handle(&self, center: Coordinate<f64>, radius: f64) {
+        let circle = web::Path::ellipse(
+            center,
+            Coordinate { x: radius, y: 0. },
+            Coordinate { x: 0., y: radius },
+        );
+        self.canvas.set_fill_color(Color::WHITE);
+        self.canvas.fill_path_obj(&circle);
+        self.set_style_for_bounding_rect();
+        self.canvas
+            .ctx
+            .set_line_dash(&js_sys::Array::new())
+            .expect("unexpected exception");
+        self.canvas.stroke_path_obj(&circle);
+    }
+
     pub fn render_selection_rect(&self, rect: Rect<i32>) {
         self.set_style_for_selection_rect();
         self.canvas.fill_rect(rect);
@@ -76,14 +153,14 @@ impl LayerHandle {
         self.canvas.clear();
     }
 
-    fn set_style_for_path(&self) {
+    fn set_style_for_path(&self, width: Width) {
         self.canvas
             .ctx
             .set_line_dash(&js_sys::Array::new())
             .expect("unexpected exception");
         self.canvas.ctx.set_line_cap("round");
         self.canvas.ctx.set_line_join("round");
-        self.canvas.ctx.set_line_width(2.0);
+        self.canvas.ctx.set_line_width(width.get().into());
     }
 
     fn set_style_for_selection_rect(&self) {
@@ -107,7 +184,24 @@ impl LayerHandle {
         self.canvas.ctx.set_line_cap("butt");
         self.canvas.ctx.set_line_join("butt");
         self.canvas.ctx.set_line_width(1.0);
-        self.canvas.set_stroke_color(Color::Black);
+        self.canvas.set_stroke_color(Color::BLACK);
+    }
+
+    fn set_style_for_lock_indicator(&self) {
+        thread_local! {
+            static LINE_DASH: js_sys::Array =
+                js_sys::Array::of2(&JsValue::from_f64(2.), &JsValue::from_f64(4.));
+        }
+        LINE_DASH.with(|val| {
+            self.canvas
+                .ctx
+                .set_line_dash(val)
+                .expect("unexpected exception");
+        });
+        self.canvas.ctx.set_line_cap("butt");
+        self.canvas.ctx.set_line_join("butt");
+        self.canvas.ctx.set_line_width(1.0);
+        self.canvas.set_stroke_color(Color::new(0, 0, 0, 128));
     }
 }
 
@@ -115,16 +209,69 @@ impl LayerHandle {
 pub struct View {
     board: web_sys::HtmlDivElement,
     pub layers: EnumMap<Layer, LayerHandle>,
-    offset: Coordinate<i32>,
+    images: ImageCache,
+    font: FontCache,
+    offset: OnScreen<Coordinate<i32>>,
+    scale: Scale,
 
     tool_radios: EnumMap<Tool, web_sys::HtmlInputElement>,
     tool_radio_labels: EnumMap<Tool, web_sys::HtmlLabelElement>,
-    pen_color_radios: EnumMap<Color, web_sys::HtmlInputElement>,
-    pen_color_radio_labels: EnumMap<Color, web_sys::HtmlLabelElement>,
+    palette: web_sys::HtmlDivElement,
+    add_palette_color_button: web_sys::HtmlButtonElement,
+    pen_color_input: web_sys::HtmlInputElement,
+    pen_alpha_input: web_sys::HtmlInputElement,
+    pen_width_input: web_sys::HtmlInputElement,
+    font_size_input: web_sys::HtmlInputElement,
+    eraser_radius_input: web_sys::HtmlInputElement,
+    lasso_partial_input: web_sys::HtmlInputElement,
+    select_all_button: web_sys::HtmlButtonElement,
+    select_same_color_button: web_sys::HtmlButtonElement,
+    invert_selection_button: web_sys::HtmlButtonElement,
+    select_on_screen_button: web_sys::HtmlButtonElement,
+    group_button: web_sys::HtmlButtonElement,
+    ungroup_button: web_sys::HtmlButtonElement,
+    lock_button: web_sys::HtmlButtonElement,
+    unlock_button: web_sys::HtmlButtonElement,
+    bring_forward_button: web_sys::HtmlButtonElement,
+    send_backward_button: web_sys::HtmlButtonElement,
+    bring_to_front_button: web_sys::HtmlButtonElement,
+    send_to_back_button: web_sys::HtmlButtonElement,
+    export_selection_svg_button: web_sys::HtmlButtonElement,
+    text_editor: web_sys::HtmlTextAreaElement,
+    doc_layer_list: web_sys::HtmlDivElement,
+    add_doc_layer_button: web_sys::HtmlButtonElement,
+    remove_doc_layer_button: web_sys::HtmlButtonElement,
+    raise_doc_layer_button: web_sys::HtmlButtonElement,
+    lower_doc_layer_button: web_sys::HtmlButtonElement,
+    rename_doc_layer_button: web_sys::HtmlButtonElement,
+    move_to_doc_layer_button: web_sys::HtmlButtonElement,
+    board_select: web_sys::HtmlSelectElement,
+    add_board_button: web_sys::HtmlButtonElement,
+    rename_board_button: web_sys::HtmlButtonElement,
+    duplicate_board_button: web_sys::HtmlButtonElement,
+    remove_board_button: web_sys::HtmlButtonElement,
+    export_svg_button: web_sys::HtmlButtonElement,
+    export_png_scope_select: web_sys::HtmlSelectElement,
+    export_png_scale_select: web_sys::HtmlSelectElement,
+    export_png_background_input: web_sys::HtmlInputElement,
+    export_png_button: web_sys::HtmlButtonElement,
+    save_file_button: web_sys::HtmlButtonElement,
+    open_file_input: web_sys::HtmlInputElement,
+    merge_file_input: web_sys::HtmlInputElement,
     clear_button: web_sys::HtmlButtonElement,
+    zoom_in_button: web_sys::HtmlButtonElement,
+    zoom_out_button: web_sys::HtmlButtonElement,
+    save_warning: web_sys::HtmlDivElement,
 }
 
 impl View {
+    /// The size of the background grid at 100% zoom, in pixels.
+    const GRID_SIZE: f64 = 20.;
+    /// The event dispatched to the board when an image has been loaded.
+    const IMAGE_LOAD_EVENT: &'static str = "papirs-imageload";
+    /// The font to rasterize texts with, in place of `sans-serif`.
+    const FONT_URL: &'static str = "assets/DejaVuSans.ttf";
+
     pub fn init() -> Self {
         web::bind_elements! {
             let board;
@@ -135,26 +282,72 @@ impl View {
             let tool_selector_radio: web_sys::HtmlInputElement;
             let tool_pen_radio: web_sys::HtmlInputElement;
             let tool_eraser_radio: web_sys::HtmlInputElement;
+            let tool_line_radio: web_sys::HtmlInputElement;
+            let tool_arrow_radio: web_sys::HtmlInputElement;
+            let tool_rectangle_radio: web_sys::HtmlInputElement;
+            let tool_ellipse_radio: web_sys::HtmlInputElement;
+            let tool_text_radio: web_sys::HtmlInputElement;
+            let tool_partial_eraser_radio: web_sys::HtmlInputElement;
+            let tool_lasso_radio: web_sys::HtmlInputElement;
 
             let tool_selector_radio_label: web_sys::HtmlLabelElement;
             let tool_pen_radio_label: web_sys::HtmlLabelElement;
             let tool_eraser_radio_label: web_sys::HtmlLabelElement;
+            let tool_line_radio_label: web_sys::HtmlLabelElement;
+            let tool_arrow_radio_label: web_sys::HtmlLabelElement;
+            let tool_rectangle_radio_label: web_sys::HtmlLabelElement;
+            let tool_ellipse_radio_label: web_sys::HtmlLabelElement;
+            let tool_text_radio_label: web_sys::HtmlLabelElement;
+            let tool_partial_eraser_radio_label: web_sys::HtmlLabelElement;
+            let tool_lasso_radio_label: web_sys::HtmlLabelElement;
 
-            let pen_color_black_radio: web_sys::HtmlInputElement;
-            let pen_color_red_radio: web_sys::HtmlInputElement;
-            let pen_color_orange_radio: web_sys::HtmlInputElement;
-            let pen_color_green_radio: web_sys::HtmlInputElement;
-            let pen_color_blue_radio: web_sys::HtmlInputElement;
-            let pen_color_sky_blue_radio: web_sys::HtmlInputElement;
-
-            let pen_color_black_radio_label: web_sys::HtmlLabelElement;
-            let pen_color_red_radio_label: web_sys::HtmlLabelElement;
-            let pen_color_orange_radio_label: web_sys::HtmlLabelElement;
-            let pen_color_green_radio_label: web_sys::HtmlLabelElement;
-            let pen_color_blue_radio_label: web_sys::HtmlLabelElement;
-            let pen_color_sky_blue_radio_label: web_sys::HtmlLabelElement;
+            let palette;
+            let add_palette_color_button;
+            let pen_color_input: web_sys::HtmlInputElement;
+            let pen_alpha_input: web_sys::HtmlInputElement;
+            let pen_width_input: web_sys::HtmlInputElement;
+            let font_size_input: web_sys::HtmlInputElement;
+            let eraser_radius_input: web_sys::HtmlInputElement;
+            let lasso_partial_input: web_sys::HtmlInputElement;
+            let select_all_button;
+            let select_same_color_button;
+            let invert_selection_button;
+            let select_on_screen_button;
+            let group_button;
+            let ungroup_button;
+            let lock_button;
+            let unlock_button;
+            let bring_forward_button;
+            let send_backward_button;
+            let bring_to_front_button;
+            let send_to_back_button;
+            let export_selection_svg_button;
+            let text_editor: web_sys::HtmlTextAreaElement;
+            let doc_layer_list;
+            let add_doc_layer_button;
+            let remove_doc_layer_button;
+            let raise_doc_layer_button;
+            let lower_doc_layer_button;
+            let rename_doc_layer_button;
+            let move_to_doc_layer_button;
+            let board_select;
+            let add_board_button;
+            let rename_board_button;
+            let duplicate_board_button;
+            let remove_board_button;
+            let export_svg_button;
+            let export_png_scope_select: web_sys::HtmlSelectElement;
+            let export_png_scale_select: web_sys::HtmlSelectElement;
+            let export_png_background_input;
+            let export_png_button;
+            let save_file_button;
+            let open_file_input: web_sys::HtmlInputElement;
+            let merge_file_input: web_sys::HtmlInputElement;
 
             let clear_button;
+            let zoom_in_button;
+            let zoom_out_button;
+            let save_warning;
         }
 
         let main_canvas = web::Canvas::from(main_canvas);
@@ -163,42 +356,99 @@ impl View {
 
         adjust_canvas_size(&board, [&main_canvas, &sub_canvas, &temp_canvas]);
 
+        pen_alpha_input.set_min("0");
+        pen_alpha_input.set_max(&u8::MAX.to_string());
+        pen_width_input.set_min(&Width::MIN.get().to_string());
+        pen_width_input.set_max(&Width::MAX.get().to_string());
+        font_size_input.set_min(&FontSize::MIN.get().to_string());
+        font_size_input.set_max(&FontSize::MAX.get().to_string());
+        eraser_radius_input.set_min(&EraserRadius::MIN.get().to_string());
+        eraser_radius_input.set_max(&EraserRadius::MAX.get().to_string());
+
+        let images = ImageCache::default();
+
         Self {
             board,
             layers: enum_map! {
-                Layer::Main => LayerHandle::new(main_canvas.clone()),
-                Layer::Sub => LayerHandle::new(sub_canvas.clone()),
-                Layer::Temp => LayerHandle::new(temp_canvas.clone()),
+                Layer::Main => LayerHandle::new(main_canvas.clone(), Rc::clone(&images)),
+                Layer::Sub => LayerHandle::new(sub_canvas.clone(), Rc::clone(&images)),
+                Layer::Temp => LayerHandle::new(temp_canvas.clone(), Rc::clone(&images)),
             },
-            offset: Coordinate::zero(),
+            images,
+            font: FontCache::default(),
+            offset: OnScreen(Coordinate::zero()),
+            scale: Scale::default(),
 
             tool_radios: enum_map! {
                 Tool::Selector => tool_selector_radio.clone(),
                 Tool::Pen => tool_pen_radio.clone(),
                 Tool::Eraser => tool_eraser_radio.clone(),
+                Tool::Line => tool_line_radio.clone(),
+                Tool::Arrow => tool_arrow_radio.clone(),
+                Tool::Rectangle => tool_rectangle_radio.clone(),
+                Tool::Ellipse => tool_ellipse_radio.clone(),
+                Tool::Text => tool_text_radio.clone(),
+                Tool::PartialEraser => tool_partial_eraser_radio.clone(),
+                Tool::Lasso => tool_lasso_radio.clone(),
             },
             tool_radio_labels: enum_map! {
                 Tool::Selector => tool_selector_radio_label.clone(),
                 Tool::Pen => tool_pen_radio_label.clone(),
                 Tool::Eraser => tool_eraser_radio_label.clone(),
+                Tool::Line => tool_line_radio_label.clone(),
+                Tool::Arrow => tool_arrow_radio_label.clone(),
+                Tool::Rectangle => tool_rectangle_radio_label.clone(),
+                Tool::Ellipse => tool_ellipse_radio_label.clone(),
+                Tool::Text => tool_text_radio_label.clone(),
+                Tool::PartialEraser => tool_partial_eraser_radio_label.clone(),
+                Tool::Lasso => tool_lasso_radio_label.clone(),
             },
-            pen_color_radios: enum_map! {
-                Color::Black => pen_color_black_radio.clone(),
-                Color::Red => pen_color_red_radio.clone(),
-                Color::Orange => pen_color_orange_radio.clone(),
-                Color::Green => pen_color_green_radio.clone(),
-                Color::Blue => pen_color_blue_radio.clone(),
-                Color::SkyBlue => pen_color_sky_blue_radio.clone(),
-            },
-            pen_color_radio_labels: enum_map! {
-                Color::Black => pen_color_black_radio_label.clone(),
-                Color::Red => pen_color_red_radio_label.clone(),
-                Color::Orange => pen_color_orange_radio_label.clone(),
-                Color::Green => pen_color_green_radio_label.clone(),
-                Color::Blue => pen_color_blue_radio_label.clone(),
-                Color::SkyBlue => pen_color_sky_blue_radio_label.clone(),
-            },
+            palette,
+            add_palette_color_button,
+            pen_color_input,
+            pen_alpha_input,
+            pen_width_input,
+            font_size_input,
+            eraser_radius_input,
+            lasso_partial_input,
+            select_all_button,
+            select_same_color_button,
+            invert_selection_button,
+            select_on_screen_button,
+            group_button,
+            ungroup_button,
+            lock_button,
+            unlock_button,
+            bring_forward_button,
+            send_backward_button,
+            bring_to_front_button,
+            send_to_back_button,
+            export_selection_svg_button,
+            text_editor,
+            doc_layer_list,
+            add_doc_layer_button,
+            remove_doc_layer_button,
+            raise_doc_layer_button,
+            lower_doc_layer_button,
+            rename_doc_layer_button,
+            move_to_doc_layer_button,
+            board_select,
+            add_board_button,
+            rename_board_button,
+            duplicate_board_button,
+            remove_board_button,
+            export_svg_button,
+            export_png_scope_select,
+            export_png_scale_select,
+            export_png_background_input,
+            export_png_button,
+            save_file_button,
+            open_file_input,
+            merge_file_input,
             clear_button,
+            zoom_in_button,
+            zoom_out_button,
+            save_warning,
         }
     }
 
@@ -221,7 +471,11 @@ impl View {
             let ctrl = Rc::clone(&ctrl);
             move |document| {
                 web::listen_event(document, "keydown", move |event: web_sys::KeyboardEvent| {
-                    ctrl.borrow_mut().on_key_down(event.into())
+                    if !web::is_on_text_field(&event)
+                        && ctrl.borrow_mut().on_key_down(event.clone().into())
+                    {
+                        event.prevent_default();
+                    }
                 });
             }
         });
@@ -234,10 +488,361 @@ impl View {
             });
         }
 
-        for (color, label) in &self.pen_color_radio_labels {
-            web::listen_event(label, "pointerdown", {
+        // The palette is rebuilt every time it changes, so the events are delegated to the container.
+        fn palette_color_of(event: &web_sys::Event) -> Option<Color> {
+            let target = event.target()?.dyn_into::<web_sys::Element>().ok()?;
+            target.get_attribute("data-color")?.parse().ok()
+        }
+
+        web::listen_event(&self.palette, "pointerdown", {
+            let ctrl = Rc::clone(&ctrl);
+            move |event: web_sys::MouseEvent| {
+                if let Some(color) = palette_color_of(&event) {
+                    ctrl.borrow_mut().set_pen_color(color);
+                }
+            }
+        });
+
+        web::listen_event(&self.palette, "contextmenu", {
+            let ctrl = Rc::clone(&ctrl);
+            move |event: web_sys::MouseEvent| {
+                if let Some(color) = palette_color_of(&event) {
+                    event.prevent_default();
+                    ctrl.borrow_mut().remove_color_from_palette(color);
+                }
+            }
+        });
+
+        web::listen_event(&self.add_palette_color_button, "pointerdown", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| ctrl.borrow_mut().add_pen_color_to_palette()
+        });
+
+        for input in [&self.pen_color_input, &self.pen_alpha_input] {
+            web::listen_event(input, "input", {
+                let ctrl = Rc::clone(&ctrl);
+                let this = self.clone();
+                move |_: web_sys::Event| {
+                    let color = match this.pen_color_input.value().parse::<Color>() {
+                        Ok(color) => color,
+                        Err(err) => return log::error!("{}", err),
+                    };
+                    let alpha = this.pen_alpha_input.value_as_number() as _;
+                    ctrl.borrow_mut().set_pen_color(color.with_alpha(alpha));
+                }
+            });
+        }
+
+        web::listen_event(&self.pen_width_input, "input", {
+            let ctrl = Rc::clone(&ctrl);
+            let input = self.pen_width_input.clone();
+            move |_: web_sys::Event| {
+                ctrl.borrow_mut()
+                    .set_pen_width(Width::new(input.value_as_number() as _))
+            }
+        });
+
+        web::listen_event(&self.font_size_input, "input", {
+            let ctrl = Rc::clone(&ctrl);
+            let input = self.font_size_input.clone();
+            move |_: web_sys::Event| {
+                ctrl.borrow_mut()
+                    .set_font_size(FontSize::new(input.value_as_number() as _))
+            }
+        });
+
+        web::listen_event(&self.eraser_radius_input, "input", {
+            let ctrl = Rc::clone(&ctrl);
+            let input = self.eraser_radius_input.clone();
+            move |_: web_sys::Event| {
+                ctrl.borrow_mut()
+                    .set_eraser_radius(EraserRadius::new(input.value_as_number() as _))
+            }
+        });
+
+        web::listen_event(&self.lasso_partial_input, "change", {
+            let ctrl = Rc::clone(&ctrl);
+            let input = self.lasso_partial_input.clone();
+            move |_: web_sys::Event| {
+                let mode = if input.checked() {
+                    LassoMode::Partial
+                } else {
+                    LassoMode::Whole
+                };
+                ctrl.borrow_mut().set_lasso_mode(mode)
+            }
+        });
+
+        web::listen_event(&self.select_all_button, "pointerdown", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| ctrl.borrow_mut().select_all_paths()
+        });
+
+        web::listen_event(&self.select_same_color_button, "pointerdown", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| ctrl.borrow_mut().select_paths_of_same_color()
+        });
+
+        web::listen_event(&self.invert_selection_button, "pointerdown", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| ctrl.borrow_mut().invert_selection()
+        });
+
+        web::listen_event(&self.select_on_screen_button, "pointerdown", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| ctrl.borrow_mut().select_paths_on_screen()
+        });
+
+        web::listen_event(&self.group_button, "pointerdown", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| ctrl.borrow_mut().group_selected_paths()
+        });
+
+        web::listen_event(&self.ungroup_button, "pointerdown", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| ctrl.borrow_mut().ungroup_selected_paths()
+        });
+
+        web::listen_event(&self.lock_button, "pointerdown", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| ctrl.borrow_mut().lock_selected_paths()
+        });
+
+        web::listen_event(&self.unlock_button, "pointerdown", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| ctrl.borrow_mut().unlock_all_paths()
+        });
+
+        for (button, restack) in [
+            (&self.bring_forward_button, Restack::Forward),
+            (&self.send_backward_button, Restack::Backward),
+            (&self.bring_to_front_button, Restack::ToFront),
+            (&self.send_to_back_button, Restack::ToBack),
+        ] {
+            web::listen_event(button, "pointerdown", {
+                let ctrl = Rc::clone(&ctrl);
+                move |_: web_sys::MouseEvent| ctrl.borrow_mut().restack_selected_paths(restack)
+            });
+        }
+
+        // The layer list is rebuilt every time it changes, so the events are delegated to the container.
+        web::listen_event(&self.doc_layer_list, "change", {
+            let ctrl = Rc::clone(&ctrl);
+            move |event: web_sys::Event| {
+                let input = match (event.target())
+                    .and_then(|t| t.dyn_into::<web_sys::HtmlInputElement>().ok())
+                {
+                    Some(input) => input,
+                    None => return,
+                };
+                let id = match (input.get_attribute("data-doc-layer-id"))
+                    .and_then(|id| id.parse::<DocLayerId>().ok())
+                {
+                    Some(id) => id,
+                    None => return,
+                };
+                let mut ctrl = ctrl.borrow_mut();
+                match input.get_attribute("data-role").as_deref() {
+                    Some("active") => ctrl.set_active_doc_layer(id),
+                    Some("visible") => ctrl.set_doc_layer_visible(id, input.checked()),
+                    Some("locked") => ctrl.set_doc_layer_locked(id, input.checked()),
+                    _ => {}
+                }
+            }
+        });
+
+        web::listen_event(&self.add_doc_layer_button, "pointerdown", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| ctrl.borrow_mut().add_doc_layer()
+        });
+
+        web::listen_event(&self.remove_doc_layer_button, "pointerdown", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| {
+                let id = ctrl.borrow().doc_layers().active;
+                ctrl.borrow_mut().remove_doc_layer(id)
+            }
+        });
+
+        for (button, up) in [
+            (&self.raise_doc_layer_button, true),
+            (&self.lower_doc_layer_button, false),
+        ] {
+            web::listen_event(button, "pointerdown", {
+                let ctrl = Rc::clone(&ctrl);
+                move |_: web_sys::MouseEvent| {
+                    let id = ctrl.borrow().doc_layers().active;
+                    ctrl.borrow_mut().move_doc_layer(id, up)
+                }
+            });
+        }
+
+        // Uses click since the prompt blocks the following events.
+        web::listen_event(&self.rename_doc_layer_button, "click", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| {
+                let layers = ctrl.borrow().doc_layers().clone();
+                let layer = layers.resolve(Some(layers.active));
+                if let Some(name) = web::prompt("Layer name", &layer.name) {
+                    ctrl.borrow_mut().rename_doc_layer(layer.id, name);
+                }
+            }
+        });
+
+        web::listen_event(&self.move_to_doc_layer_button, "pointerdown", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| {
+                let id = ctrl.borrow().doc_layers().active;
+                ctrl.borrow_mut().move_selected_paths_to_doc_layer(id)
+            }
+        });
+
+        web::listen_event(&self.board_select, "change", {
+            let ctrl = Rc::clone(&ctrl);
+            let board_select = self.board_select.clone();
+            move |_: web_sys::Event| {
+                if let Ok(id) = board_select.value().parse::<BoardId>() {
+                    ctrl.borrow_mut().switch_board(id);
+                }
+            }
+        });
+
+        web::listen_event(&self.add_board_button, "pointerdown", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| ctrl.borrow_mut().add_board()
+        });
+
+        web::listen_event(&self.rename_board_button, "click", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| {
+                let boards = ctrl.borrow().boards().clone();
+                if let Some(board) = boards.get(boards.active) {
+                    if let Some(name) = web::prompt("Board name", &board.name) {
+                        ctrl.borrow_mut().rename_board(board.id, name);
+                    }
+                }
+            }
+        });
+
+        web::listen_event(&self.duplicate_board_button, "pointerdown", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| {
+                let id = ctrl.borrow().boards().active;
+                ctrl.borrow_mut().duplicate_board(id)
+            }
+        });
+
+        web::listen_event(&self.remove_board_button, "click", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| {
+                let boards = ctrl.borrow().boards().clone();
+                if boards.list.len() <= 1 {
+                    return;
+                }
+                if let Some(board) = boards.get(boards.active) {
+                    let message =
+                        format!("Delete the board \"{}\" with all its paths?", board.name);
+                    if web::confirm(&message) {
+                        ctrl.borrow_mut().remove_board(board.id);
+                    }
+                }
+            }
+        });
+
+        // The editor may lose the focus while the controller is finishing editing by itself.
+        web::listen_event(&self.text_editor, "blur", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::FocusEvent| {
+                if let Ok(mut ctrl) = ctrl.try_borrow_mut() {
+                    ctrl.finish_text_editing();
+                }
+            }
+        });
+
+        web::listen_event(&self.text_editor, "keydown", {
+            let ctrl = Rc::clone(&ctrl);
+            move |event: web_sys::KeyboardEvent| {
+                if event.key() == "Escape" || (event.key() == "Enter" && event.ctrl_key()) {
+                    event.prevent_default();
+                    ctrl.borrow_mut().finish_text_editing();
+                }
+            }
+        });
+
+        web::listen_event(&self.text_editor, "input", {
+            let this = self.clone();
+            move |_: web_sys::Event| this.fit_text_editor()
+        });
+
+        for (button, scope) in [
+            (&self.export_svg_button, ExportScope::Board),
+            (&self.export_selection_svg_button, ExportScope::Selection),
+        ] {
+            web::listen_event(button, "click", {
+                let ctrl = Rc::clone(&ctrl);
+                let this = self.clone();
+                move |_: web_sys::MouseEvent| this.export_svg(&ctrl.borrow(), scope)
+            });
+        }
+
+        web::listen_event(&self.export_png_button, "click", {
+            let ctrl = Rc::clone(&ctrl);
+            let this = self.clone();
+            move |_: web_sys::MouseEvent| {
+                let scope = match this.export_png_scope_select.value().as_str() {
+                    "selection" => ExportScope::Selection,
+                    "viewport" => ExportScope::Viewport,
+                    _ => ExportScope::Board,
+                };
+                let scale = this.export_png_scale_select.value().parse().unwrap_or(1.);
+                let background = if this.export_png_background_input.checked() {
+                    Some(Color::WHITE)
+                } else {
+                    None
+                };
+                this.export_png(&ctrl.borrow(), scope, scale, background);
+            }
+        });
+
+        web::listen_event(&self.save_file_button, "click", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| {
+                let ctrl = ctrl.borrow();
+                match ctrl.document().encode() {
+                    Ok(bytes) => {
+                        let file_name = Self::export_file_name(&ctrl, Document::EXTENSION);
+                        web::download(&file_name, Document::MIME_TYPE, &bytes);
+                    }
+                    Err(err) => log::error!("failed to save the file: {}", err),
+                }
+            }
+        });
+
+        for (input, merge) in [
+            (&self.open_file_input, false),
+            (&self.merge_file_input, true),
+        ] {
+            web::listen_event(input, "change", {
                 let ctrl = Rc::clone(&ctrl);
-                move |_: web_sys::MouseEvent| ctrl.borrow_mut().set_pen_color(color)
+                let input = input.clone();
+                move |_: web_sys::Event| {
+                    let file = match input.files().and_then(|files| files.get(0)) {
+                        Some(file) => file,
+                        None => return,
+                    };
+                    input.set_value(""); // allows to open the same file again
+                    let ctrl = Rc::clone(&ctrl);
+                    wasm_bindgen_futures::spawn_local(async move {
+                        let doc = match web::read_file(&file).await {
+                            Ok(bytes) => Document::decode(&bytes),
+                            Err(err) => Err(err),
+                        };
+                        match doc {
+                            Ok(doc) => ctrl.borrow_mut().open_document(doc, merge),
+                            Err(err) => log::error!("failed to open `{}`: {}", file.name(), err),
+                        }
+                    });
+                }
             });
         }
 
@@ -246,15 +851,101 @@ impl View {
             move |_: web_sys::MouseEvent| ctrl.borrow_mut().clear_paths()
         });
 
+        web::listen_event(&self.zoom_in_button, "pointerdown", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| ctrl.borrow_mut().zoom_in()
+        });
+
+        web::listen_event(&self.zoom_out_button, "pointerdown", {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::MouseEvent| ctrl.borrow_mut().zoom_out()
+        });
+
         web::listen_event(&self.board, "wheel", {
             let ctrl = Rc::clone(&ctrl);
             let this = self.clone();
             move |event: web_sys::WheelEvent| {
+                if event.ctrl_key() {
+                    // prevents the browser from zooming the whole page
+                    event.prevent_default();
+                }
                 ctrl.borrow_mut()
                     .on_wheel(web::WheelEvent::new(event, this.size()))
             }
         });
 
+        web::DOCUMENT.with({
+            let ctrl = Rc::clone(&ctrl);
+            let this = self.clone();
+            move |document| {
+                for (kind, cut) in [("copy", false), ("cut", true)] {
+                    let ctrl = Rc::clone(&ctrl);
+                    let this = this.clone();
+                    web::listen_event(document, kind, move |event: web_sys::ClipboardEvent| {
+                        if web::is_on_text_field(&event) {
+                            return;
+                        }
+                        let data = match event.clipboard_data() {
+                            Some(data) => data,
+                            None => return,
+                        };
+                        let clip = if cut {
+                            ctrl.borrow_mut().cut()
+                        } else {
+                            ctrl.borrow().copy()
+                        };
+                        if let Some(clip) = clip {
+                            // prevents the browser from copying the page's selection instead
+                            event.prevent_default();
+                            this.write_clip(&data, &clip);
+                        }
+                    });
+                }
+
+                web::listen_event(document, "paste", move |event: web_sys::ClipboardEvent| {
+                    if web::is_on_text_field(&event) {
+                        return;
+                    }
+                    let data = match event.clipboard_data() {
+                        Some(data) => data,
+                        None => return,
+                    };
+                    if let Some(clip) = Self::read_clip(&data) {
+                        ctrl.borrow_mut().paste(clip);
+                    } else if let Some(files) = data.files() {
+                        this.insert_image_files(&ctrl, &files, None);
+                        Self::import_svg_files(&ctrl, &files, None);
+                    }
+                });
+            }
+        });
+
+        // Prevents the browser from opening the dropped file.
+        web::listen_event(&self.board, "dragover", |event: web_sys::DragEvent| {
+            event.prevent_default()
+        });
+
+        web::listen_event(&self.board, "drop", {
+            let ctrl = Rc::clone(&ctrl);
+            let this = self.clone();
+            move |event: web_sys::DragEvent| {
+                event.prevent_default();
+                let coord = OnScreen(Coordinate {
+                    x: event.offset_x(),
+                    y: event.offset_y(),
+                });
+                if let Some(files) = event.data_transfer().and_then(|d| d.files()) {
+                    this.insert_image_files(&ctrl, &files, Some(coord));
+                    Self::import_svg_files(&ctrl, &files, Some(coord));
+                }
+            }
+        });
+
+        web::listen_event(&self.board, Self::IMAGE_LOAD_EVENT, {
+            let ctrl = Rc::clone(&ctrl);
+            move |_: web_sys::Event| ctrl.borrow().rerender()
+        });
+
         web::listen_event(&self.board, "pointerdown", {
             let ctrl = Rc::clone(&ctrl);
             move |event: web_sys::MouseEvent| ctrl.borrow_mut().on_pointer_down(event.into())
@@ -271,20 +962,193 @@ impl View {
         });
     }
 
+    /// Puts the clip on the clipboard, along with its SVG for other applications.
+    fn write_clip(&self, data: &web_sys::DataTransfer, clip: &Clip) {
+        let mut encoded = String::new();
+        if let Err(err) = web::encode_into(clip, &mut encoded) {
+            return log::error!("failed to encode the clip: {}", err);
+        }
+        data.set_data(Clip::MIME_TYPE, &encoded)
+            .expect("unexpected exception");
+        if let Some(svg) = svg::serialize(&clip.paths, &clip.images, |text| self.measure_text(text))
+        {
+            for mime_type in ["image/svg+xml", "text/plain"] {
+                data.set_data(mime_type, &svg)
+                    .expect("unexpected exception");
+            }
+        }
+    }
+
+    /// Lets the user download the paths in the scope as an SVG file, if any.
+    fn export_svg(&self, ctrl: &Controller, scope: ExportScope) {
+        let (clip, _) = match ctrl.export_paths(scope) {
+            Some(export) => export,
+            None => return,
+        };
+        if let Some(svg) = svg::serialize(&clip.paths, &clip.images, |text| self.measure_text(text))
+        {
+            let file_name = Self::export_file_name(ctrl, "svg");
+            web::download(&file_name, "image/svg+xml", svg.as_bytes());
+        }
+    }
+
+    /// Lets the user download the paths in the scope as a PNG file,
+    /// rendered at `scale` over `background` if any.
+    fn export_png(
+        &self,
+        ctrl: &Controller,
+        scope: ExportScope,
+        scale: f64,
+        background: Option<Color>,
+    ) {
+        let (clip, rect) = match ctrl.export_paths(scope) {
+            Some(export) => export,
+            None => return,
+        };
+        let file_name = Self::export_file_name(ctrl, "png");
+        let font = Rc::clone(&self.font);
+        wasm_bindgen_futures::spawn_local(async move {
+            let cached = font.borrow().clone();
+            let font = match cached {
+                Some(font) => font,
+                None => match web::fetch(Self::FONT_URL).await {
+                    Ok(bytes) => Rc::clone(font.borrow_mut().insert(bytes.into())),
+                    Err(err) => return log::error!("failed to export PNG: {}", err),
+                },
+            };
+            let paths = clip.paths.iter().map(|path| path.get().get());
+            match raster::render_png(paths, &clip.images, &font, rect, scale, background) {
+                Ok(png) => web::download(&file_name, "image/png", &png),
+                Err(err) => log::error!("failed to export PNG: {}", err),
+            }
+        });
+    }
+
+    /// Returns the name of an exported file, after the active board.
+    fn export_file_name(ctrl: &Controller, extension: &str) -> String {
+        let boards = ctrl.boards();
+        let name = boards
+            .get(boards.active)
+            .map_or("papirs", |board| &board.name);
+        format!("{}.{}", name, extension)
+    }
+
+    /// Reads the clip from the clipboard, if any.
+    fn read_clip(data: &web_sys::DataTransfer) -> Option<Clip> {
+        let encoded = data
+            .get_data(Clip::MIME_TYPE)
+            .expect("unexpected exception");
+        if encoded.is_empty() {
+            return None;
+        }
+        web::decode(&encoded)
+            .map_err(|err| log::error!("failed to decode the clip: {}", err))
+            .ok()
+    }
+
+    /// Reads PNG or JPEG images among the files and requests the controller to insert them
+    /// at `coord` (or the center of the screen if [`None`]) once decoded.
+    fn insert_image_files(
+        &self,
+        ctrl: &Rc<RefCell<Controller>>,
+        files: &web_sys::FileList,
+        coord: Option<OnScreen<Coordinate<i32>>>,
+    ) {
+        let files = (0..files.length())
+            .filter_map(|i| files.get(i))
+            .filter(|file| ImageData::MIME_TYPES.contains(&file.type_().as_str()));
+        for file in files {
+            let ctrl = Rc::clone(ctrl);
+            let images = Rc::clone(&self.images);
+            wasm_bindgen_futures::spawn_local(async move {
+                let (data, image) = match web::read_image(&file).await {
+                    Ok(image) => image,
+                    Err(err) => return log::error!("{}", err),
+                };
+                let size = Coordinate {
+                    x: image.natural_width(),
+                    y: image.natural_height(),
+                };
+                images.borrow_mut().insert(data.hash(), image);
+                ctrl.borrow_mut().insert_image(data, size, coord);
+            });
+        }
+    }
+
+    /// Reads SVG files among the files and requests the controller to insert their drawings
+    /// at `coord` (or the pointer if [`None`]).
+    fn import_svg_files(
+        ctrl: &Rc<RefCell<Controller>>,
+        files: &web_sys::FileList,
+        coord: Option<OnScreen<Coordinate<i32>>>,
+    ) {
+        let files = (0..files.length())
+            .filter_map(|i| files.get(i))
+            .filter(|file| file.type_() == "image/svg+xml" || file.name().ends_with(".svg"));
+        for file in files {
+            let ctrl = Rc::clone(ctrl);
+            wasm_bindgen_futures::spawn_local(async move {
+                let paths = match web::read_file(&file).await {
+                    Ok(bytes) => String::from_utf8(bytes)
+                        .map_err(anyhow::Error::from)
+                        .and_then(|source| svg::parse(&source)),
+                    Err(err) => Err(err),
+                };
+                match paths {
+                    Ok(paths) => ctrl.borrow_mut().import_paths(paths, coord),
+                    Err(err) => log::error!("failed to import `{}`: {}", file.name(), err),
+                }
+            });
+        }
+    }
+
+    /// Decodes the image data to be rendered, unless it has already been.
+    pub fn register_image(&self, hash: ImageHash, data: &ImageData) {
+        if self.images.borrow().contains_key(&hash) {
+            return;
+        }
+        let image = web::create_image(data);
+        web::listen_event(&image, "load", {
+            let board = self.board.clone();
+            move |_: web_sys::Event| {
+                let event =
+                    web_sys::Event::new(Self::IMAGE_LOAD_EVENT).expect("unexpected exception");
+                board.dispatch_event(&event).expect("unexpected exception");
+            }
+        });
+        self.images.borrow_mut().insert(hash, image);
+    }
+
     pub fn size(&self) -> OnScreen<Coordinate<u32>> {
         self.layers[Layer::Main].canvas.size()
     }
 
-    pub fn translate(&mut self, delta: Coordinate<i32>) {
+    /// Makes the board's origin be at `offset` on the screen and scaled by `scale`.
+    ///
+    /// Any extra transformation applied to each layer by [`LayerHandle::translate`] is preserved.
+    pub fn transform(&mut self, offset: OnScreen<Coordinate<i32>>, scale: Scale) {
+        let rel_scale = scale.get() / self.scale.get();
+        let delta = OnScreen(Coordinate {
+            x: f64::from(offset.0.x) - f64::from(self.offset.0.x) * rel_scale,
+            y: f64::from(offset.0.y) - f64::from(self.offset.0.y) * rel_scale,
+        });
         for layer in self.layers.values() {
-            layer.translate(delta);
+            layer.canvas.transform_on_screen(rel_scale, delta);
         }
-        self.offset = self.offset + delta;
-        self.board
-            .style()
+        self.offset = offset;
+        self.scale = scale;
+
+        let style = self.board.style();
+        style
             .set_property(
                 "background-position",
-                &format!("{}px {}px", self.offset.x, self.offset.y),
+                &format!("{}px {}px", self.offset.0.x, self.offset.0.y),
+            )
+            .expect("unexpected exception");
+        style
+            .set_property(
+                "background-size",
+                &format!("{}px", Self::GRID_SIZE * self.scale.get()),
             )
             .expect("unexpected exception");
     }
@@ -293,7 +1157,198 @@ impl View {
         self.tool_radios[tool].set_checked(true);
     }
 
+    /// Rebuilds the layer list, showing the top layer first.
+    pub fn render_boards(&self, boards: &Boards) {
+        self.board_select.set_inner_html("");
+        for board in &boards.list {
+            let option = web::create_element::<web_sys::HtmlOptionElement>("option");
+            option.set_value(&board.id.to_string());
+            option.set_text(&board.name);
+            option.set_selected(board.id == boards.active);
+            self.board_select
+                .append_with_node_1(&option)
+                .expect("unexpected exception");
+        }
+    }
+
+    pub fn render_doc_layers(&self, layers: &DocLayers) {
+        self.doc_layer_list.set_inner_html("");
+        for layer in layers.list.iter().rev() {
+            let id = layer.id.to_string();
+            let row = web::create_element::<web_sys::HtmlDivElement>("div");
+            row.set_class_name("doc-layer");
+
+            let input = |type_: &str, role: &str, title: &str, checked: bool| {
+                let input = web::create_element::<web_sys::HtmlInputElement>("input");
+                input.set_type(type_);
+                input.set_title(title);
+                input.set_checked(checked);
+                input
+                    .set_attribute("data-doc-layer-id", &id)
+                    .expect("unexpected exception");
+                input
+                    .set_attribute("data-role", role)
+                    .expect("unexpected exception");
+                input
+            };
+            let radio = input(
+                "radio",
+                "active",
+                "Draw into this layer",
+                layer.id == layers.active,
+            );
+            radio.set_name("doc-layer");
+            radio.set_id(&format!("doc-layer-{}-radio", id));
+
+            let label = web::create_element::<web_sys::HtmlLabelElement>("label");
+            label.set_html_for(&radio.id());
+            label.set_text_content(Some(&layer.name));
+
+            let visible = input("checkbox", "visible", "Visible", layer.visible);
+            let locked = input("checkbox", "locked", "Locked", layer.locked);
+
+            row.append_with_node_4(&radio, &label, &visible, &locked)
+                .expect("unexpected exception");
+            self.doc_layer_list
+                .append_with_node_1(&row)
+                .expect("unexpected exception");
+        }
+    }
+
+    pub fn render_palette(&self, palette: &Palette) {
+        self.palette.set_inner_html("");
+        for (i, color) in palette.0.iter().enumerate() {
+            let id = format!("palette-color-{}-radio", i);
+            let color = color.to_string();
+
+            let radio = web::create_element::<web_sys::HtmlInputElement>("input");
+            radio.set_id(&id);
+            radio.set_type("radio");
+            radio.set_name("pen-color");
+            radio.set_value(&color);
+
+            let label = web::create_element::<web_sys::HtmlLabelElement>("label");
+            label.set_html_for(&id);
+            label.set_title(&color);
+            label
+                .set_attribute("data-color", &color)
+                .expect("unexpected exception");
+            label
+                .style()
+                .set_property("background-color", &color)
+                .expect("unexpected exception");
+
+            self.palette
+                .append_with_node_2(&radio, &label)
+                .expect("unexpected exception");
+        }
+    }
+
     pub fn select_pen_color(&self, color: Color) {
-        self.pen_color_radios[color].set_checked(true);
+        let children = self.palette.children();
+        let value = color.to_string();
+        for i in 0..children.length() {
+            if let Some(radio) =
+                (children.item(i)).and_then(|e| e.dyn_into::<web_sys::HtmlInputElement>().ok())
+            {
+                radio.set_checked(radio.value() == value);
+            }
+        }
+        let (r, g, b) = color.rgb();
+        self.pen_color_input
+            .set_value(&format!("#{:02x}{:02x}{:02x}", r, g, b));
+        self.pen_alpha_input
+            .set_value_as_number(color.alpha().into());
+    }
+
+    pub fn select_pen_width(&self, width: Width) {
+        self.pen_width_input.set_value_as_number(width.get().into());
+    }
+
+    pub fn select_font_size(&self, size: FontSize) {
+        self.font_size_input.set_value_as_number(size.get().into());
+    }
+
+    pub fn select_eraser_radius(&self, radius: EraserRadius) {
+        self.eraser_radius_input
+            .set_value_as_number(radius.get().into());
+    }
+
+    pub fn select_lasso_mode(&self, mode: LassoMode) {
+        self.lasso_partial_input
+            .set_checked(mode == LassoMode::Partial);
+    }
+
+    pub fn measure_text(&self, text: &Text) -> Coordinate<f64> {
+        self.layers[Layer::Main].canvas.measure_text(text)
+    }
+
+    /// Shows the warning that changes are not saved for `cause`, which stays until reloaded.
+    pub fn warn_save_failure(&self, cause: &str) {
+        let message = format!("Changes are not saved: {}", cause);
+        self.save_warning.set_text_content(Some(&message));
+        self.save_warning
+            .style()
+            .set_property("display", "block")
+            .expect("unexpected exception");
+    }
+
+    /// Shows the text editor with `content` and focuses it.
+    ///
+    /// It should be placed by [`place_text_editor`](Self::place_text_editor) afterwards.
+    pub fn open_text_editor(&self, content: &str, color: Color) {
+        self.text_editor.set_value(content);
+        let style = self.text_editor.style();
+        style
+            .set_property("color", &color.to_string())
+            .expect("unexpected exception");
+        style
+            .set_property("display", "block")
+            .expect("unexpected exception");
+        self.text_editor.focus().expect("unexpected exception");
+    }
+
+    /// Moves the text editor so that its top-left corner is at `coord` on the screen.
+    pub fn place_text_editor(&self, coord: OnScreen<Coordinate<f64>>, font_size: f64) {
+        let style = self.text_editor.style();
+        for (name, value) in [
+            ("left", coord.0.x),
+            ("top", coord.0.y),
+            ("font-size", font_size),
+        ] {
+            style
+                .set_property(name, &format!("{}px", value))
+                .expect("unexpected exception");
+        }
+        self.fit_text_editor();
+    }
+
+    /// Hides the text editor and returns its content.
+    pub fn close_text_editor(&self) -> String {
+        self.text_editor
+            .style()
+            .set_property("display", "none")
+            .expect("unexpected exception");
+        let content = self.text_editor.value();
+        self.text_editor.set_value("");
+        content
+    }
+
+    /// Resizes the text editor to fit its content.
+    fn fit_text_editor(&self) {
+        let style = self.text_editor.style();
+        for name in ["width", "height"] {
+            style.set_property(name, "0").expect("unexpected exception");
+        }
+        let (width, height) = (
+            self.text_editor.scroll_width(),
+            self.text_editor.scroll_height(),
+        );
+        style
+            .set_property("width", &format!("{}px", width))
+            .expect("unexpected exception");
+        style
+            .set_property("height", &format!("{}px", height))
+            .expect("unexpected exception");
     }
 }
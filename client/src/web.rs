@@ -1,15 +1,16 @@
 //! Web API wrappers.
 
 use crate::{
-    common::{Color, OnScreen},
+    common::{Color, ImageData, OnScreen, Segment, Text},
+    storage::{MemoryStorage, Storage},
     utils,
 };
-use anyhow::{anyhow, Result};
+use anyhow::{anyhow, bail, Result};
 use geo::{CoordNum, Coordinate, LineString, Rect};
-use itertools::Itertools as _;
 use serde::{Deserialize, Serialize};
-use std::{cell::RefCell, mem};
-use wasm_bindgen::{convert::FromWasmAbi, prelude::*, JsCast as _};
+use std::{cell::Cell, fmt, future::Future, io, mem, rc::Rc};
+use wasm_bindgen::{convert::FromWasmAbi, prelude::*, JsCast};
+use wasm_bindgen_futures::JsFuture;
 
 thread_local! {
     pub static WINDOW: web_sys::Window = web_sys::window().expect("no window");
@@ -28,6 +29,76 @@ macro_rules! bind_elements {
 }
 pub(crate) use bind_elements;
 
+pub fn create_element<T>(tag: &str) -> T
+where
+    T: JsCast,
+{
+    DOCUMENT
+        .with(|d| d.create_element(tag))
+        .expect("unexpected exception")
+        .dyn_into()
+        .expect("element type mismatch")
+}
+
+/// Creates an image element which shows the image data.
+pub fn create_image(data: &ImageData) -> web_sys::HtmlImageElement {
+    let image = create_element::<web_sys::HtmlImageElement>("img");
+    image.set_src(&data.url());
+    image
+}
+
+/// Reads the whole content of the file.
+pub async fn read_file(file: &web_sys::File) -> Result<Vec<u8>> {
+    let buf = JsFuture::from(file.array_buffer())
+        .await
+        .map_err(|e| anyhow!("failed to read file: {:?}", e))?;
+    Ok(js_sys::Uint8Array::new(&buf).to_vec())
+}
+
+/// Fetches the whole content of the resource at `url`.
+pub async fn fetch(url: &str) -> Result<Vec<u8>> {
+    let js_err = |e: JsValue| anyhow!("failed to fetch `{}`: {:?}", url, e);
+    let response = JsFuture::from(WINDOW.with(|w| w.fetch_with_str(url)))
+        .await
+        .map_err(js_err)?
+        .unchecked_into::<web_sys::Response>();
+    if !response.ok() {
+        bail!("failed to fetch `{}`: status {}", url, response.status());
+    }
+    let buf = JsFuture::from(response.array_buffer().map_err(js_err)?)
+        .await
+        .map_err(js_err)?;
+    Ok(js_sys::Uint8Array::new(&buf).to_vec())
+}
+
+/// Reads the image file and waits for it to be decoded.
+pub async fn read_image(file: &web_sys::File) -> Result<(ImageData, web_sys::HtmlImageElement)> {
+    let data = ImageData {
+        mime_type: file.type_(),
+        bytes: read_file(file).await?,
+    };
+    let image = create_image(&data);
+    JsFuture::from(image.decode())
+        .await
+        .map_err(|e| anyhow!("failed to decode image: {:?}", e))?;
+    Ok((data, image))
+}
+
+/// Lets the user download the data as a file.
+pub fn download(file_name: &str, mime_type: &str, data: &[u8]) {
+    let blob = web_sys::Blob::new_with_u8_array_sequence_and_options(
+        &js_sys::Array::of1(&js_sys::Uint8Array::from(data)),
+        web_sys::BlobPropertyBag::new().type_(mime_type),
+    )
+    .expect("unexpected exception");
+    let url = web_sys::Url::create_object_url_with_blob(&blob).expect("unexpected exception");
+    let anchor = create_element::<web_sys::HtmlAnchorElement>("a");
+    anchor.set_href(&url);
+    anchor.set_download(file_name);
+    anchor.click();
+    web_sys::Url::revoke_object_url(&url).expect("unexpected exception");
+}
+
 #[derive(Clone, Copy, PartialEq, Eq, Hash, Debug)]
 pub enum MouseButton {
     Left,
@@ -39,6 +110,8 @@ pub enum MouseButton {
 pub struct MouseEvent {
     pub button: MouseButton,
     pub coord: OnScreen<Coordinate<i32>>,
+    pub shift_key: bool,
+    pub alt_key: bool,
 }
 
 impl From<web_sys::MouseEvent> for MouseEvent {
@@ -53,6 +126,8 @@ impl From<web_sys::MouseEvent> for MouseEvent {
                 x: event.offset_x(),
                 y: event.offset_y(),
             }),
+            shift_key: event.shift_key(),
+            alt_key: event.alt_key(),
         }
     }
 }
@@ -60,6 +135,8 @@ impl From<web_sys::MouseEvent> for MouseEvent {
 #[derive(Clone, Debug)]
 pub struct WheelEvent {
     pub delta: OnScreen<Coordinate<i32>>,
+    pub coord: OnScreen<Coordinate<i32>>,
+    pub ctrl_key: bool,
 }
 
 impl WheelEvent {
@@ -77,14 +154,43 @@ impl WheelEvent {
         if event.shift_key() {
             mem::swap(&mut delta.0.x, &mut delta.0.y);
         }
-        Self { delta }
+        Self {
+            delta,
+            coord: OnScreen(Coordinate {
+                x: event.offset_x(),
+                y: event.offset_y(),
+            }),
+            ctrl_key: event.ctrl_key(),
+        }
     }
 }
 
+/// Asks the user to input a text, or returns [`None`] if cancelled.
+pub fn prompt(message: &str, default: &str) -> Option<String> {
+    WINDOW
+        .with(|w| w.prompt_with_message_and_default(message, default))
+        .expect("unexpected exception")
+}
+
+/// Asks the user to confirm the message.
+pub fn confirm(message: &str) -> bool {
+    WINDOW
+        .with(|w| w.confirm_with_message(message))
+        .expect("unexpected exception")
+}
+
+/// Returns whether the event is targeted at a text field, in which case keys should not be
+/// handled as shortcuts.
+pub fn is_on_text_field(event: &web_sys::Event) -> bool {
+    matches!(event.target(), Some(target) if target.has_type::<web_sys::HtmlTextAreaElement>())
+}
+
 #[derive(Clone, Debug)]
 pub struct KeyboardEvent {
     pub key: String,
     pub ctrl_key: bool,
+    pub shift_key: bool,
+    pub alt_key: bool,
 }
 
 impl From<web_sys::KeyboardEvent> for KeyboardEvent {
@@ -92,6 +198,8 @@ impl From<web_sys::KeyboardEvent> for KeyboardEvent {
         Self {
             key: event.key(),
             ctrl_key: event.ctrl_key(),
+            shift_key: event.shift_key(),
+            alt_key: event.alt_key(),
         }
     }
 }
@@ -115,40 +223,38 @@ pub fn listen_event<E>(
 #[derive(Clone, Debug)]
 pub struct Path(web_sys::Path2d);
 
-impl<T> From<&'_ LineString<T>> for Path
-where
-    T: CoordNum,
-    f64: From<T>,
-{
-    fn from(coords: &LineString<T>) -> Self {
+impl Path {
+    /// Creates a path of the segments.
+    pub fn from_segments(segments: impl IntoIterator<Item = Segment>) -> Self {
         let obj = web_sys::Path2d::new().expect("unexpected exception");
-        for pair in coords.0.iter().copied().tuple_windows().with_position() {
-            use itertools::Position;
-            match pair {
-                Position::Only((c_0, c_1)) => {
-                    obj.move_to(c_0.x.into(), c_0.y.into());
-                    obj.line_to(c_1.x.into(), c_1.y.into());
-                }
-                Position::First((c_0, c_1)) => {
-                    let c_0 = utils::coord_map_scalars(c_0, f64::from);
-                    let c_1 = utils::coord_map_scalars(c_1, f64::from);
-                    let mid = (c_0 + c_1) / 2.;
-                    obj.move_to(c_0.x, c_0.y);
-                    obj.line_to(mid.x, mid.y);
-                }
-                Position::Middle((c_0, c_1)) => {
-                    let c_0 = utils::coord_map_scalars(c_0, f64::from);
-                    let c_1 = utils::coord_map_scalars(c_1, f64::from);
-                    let mid = (c_0 + c_1) / 2.;
-                    obj.quadratic_curve_to(c_0.x, c_0.y, mid.x, mid.y);
-                }
-                Position::Last((_, c_1)) => {
-                    obj.line_to(c_1.x.into(), c_1.y.into());
+        for segment in segments {
+            match segment {
+                Segment::MoveTo(c) => obj.move_to(c.x, c.y),
+                Segment::LineTo(c) => obj.line_to(c.x, c.y),
+                Segment::QuadTo(c_1, end) => obj.quadratic_curve_to(c_1.x, c_1.y, end.x, end.y),
+                Segment::CubicTo(c_1, c_2, end) => {
+                    obj.bezier_curve_to(c_1.x, c_1.y, c_2.x, c_2.y, end.x, end.y);
                 }
+                Segment::Close => obj.close_path(),
             }
         }
         Self(obj)
     }
+
+    /// Creates a path of straight line segments.
+    pub fn polyline<T>(coords: &LineString<T>) -> Self
+    where
+        T: CoordNum,
+        f64: From<T>,
+    {
+        Self::from_segments(Segment::polyline(coords))
+    }
+
+    /// Creates an ellipse `center + u cos θ + v sin θ` (0 ≤ θ < 2π)
+    /// approximated by four cubic Bézier curves.
+    pub fn ellipse(center: Coordinate<f64>, u: Coordinate<f64>, v: Coordinate<f64>) -> Self {
+        Self::from_segments(Segment::ellipse(center, u, v))
+    }
 }
 
 impl<T> From<Rect<T>> for Path
@@ -214,16 +320,121 @@ impl Canvas {
             .expect("unexpected exception");
     }
 
+    /// Scales and then translates the current transformation in the screen's coordinate system,
+    /// unlike [`translate`](Self::translate) which works in the current (board's) one.
+    pub fn transform_on_screen(&self, scale: f64, delta: OnScreen<Coordinate<f64>>) {
+        let mat = self.ctx.get_transform().expect("unexpected exception");
+        self.ctx
+            .set_transform(
+                mat.a() * scale,
+                mat.b() * scale,
+                mat.c() * scale,
+                mat.d() * scale,
+                mat.e() * scale + delta.0.x,
+                mat.f() * scale + delta.0.y,
+            )
+            .expect("unexpected exception");
+    }
+
     pub fn set_stroke_color(&self, color: Color) {
-        let (r, g, b) = color.rgb();
-        let style = JsValue::from_str(&format!("rgb({},{},{})", r, g, b));
+        let style = JsValue::from_str(&color.to_string());
         self.ctx.set_stroke_style(&style);
     }
 
+    pub fn set_fill_color(&self, color: Color) {
+        let style = JsValue::from_str(&color.to_string());
+        self.ctx.set_fill_style(&style);
+    }
+
+    /// Returns the size of the text when rendered without stretching.
+    pub fn measure_text(&self, text: &Text) -> Coordinate<f64> {
+        self.ctx.set_font(&text.font());
+        let width = text
+            .lines()
+            .map(|line| {
+                let metrics = self.ctx.measure_text(line).expect("unexpected exception");
+                metrics.width()
+            })
+            .fold(0., f64::max);
+        let height = text.lines().count() as f64 * text.line_height();
+        Coordinate {
+            x: width,
+            y: height,
+        }
+    }
+
+    /// Applies the transformation which maps the rectangle `(0, 0)`-`size` onto
+    /// the parallelogram with the corner `origin` and the two edges `u` and `v` from it.
+    fn transform_onto(
+        &self,
+        size: Coordinate<f64>,
+        origin: Coordinate<f64>,
+        u: Coordinate<f64>,
+        v: Coordinate<f64>,
+    ) {
+        let (width, height) = (size.x.max(1.), size.y.max(1.));
+        self.ctx
+            .transform(
+                u.x / width,
+                u.y / width,
+                v.x / height,
+                v.y / height,
+                origin.x,
+                origin.y,
+            )
+            .expect("unexpected exception");
+    }
+
+    /// Fills the text, stretching it onto the parallelogram with the corner `origin` and
+    /// the two edges `u` and `v` from it.
+    pub fn fill_text(
+        &self,
+        text: &Text,
+        origin: Coordinate<f64>,
+        u: Coordinate<f64>,
+        v: Coordinate<f64>,
+    ) {
+        let size = self.measure_text(text);
+        self.ctx.save();
+        self.transform_onto(size, origin, u, v);
+        self.ctx.set_text_baseline("top");
+        for (i, line) in text.lines().enumerate() {
+            self.ctx
+                .fill_text(line, 0., i as f64 * text.line_height())
+                .expect("unexpected exception");
+        }
+        self.ctx.restore();
+    }
+
+    /// Draws the image, stretching it onto the parallelogram with the corner `origin` and
+    /// the two edges `u` and `v` from it.
+    pub fn draw_image(
+        &self,
+        image: &web_sys::HtmlImageElement,
+        origin: Coordinate<f64>,
+        u: Coordinate<f64>,
+        v: Coordinate<f64>,
+    ) {
+        let size = Coordinate {
+            x: image.natural_width().into(),
+            y: image.natural_height().into(),
+        };
+        self.ctx.save();
+        self.transform_onto(size, origin, u, v);
+        self.ctx
+            .draw_image_with_html_image_element(image, 0., 0.)
+            .expect("unexpected exception");
+        self.ctx.restore();
+    }
+
     pub fn stroke_path_obj(&self, path: &Path) {
         self.ctx.stroke_with_path(&path.0);
     }
 
+    pub fn fill_path_obj(&self, path: &Path) {
+        self.ctx.fill_with_path_2d(&path.0);
+    }
+
     pub fn stroke_curve(
         &self,
         start: Coordinate<i32>,
@@ -252,76 +463,336 @@ impl Canvas {
 
     pub fn clear(&self) {
         let canvas = self.canvas();
-        let mat = self.ctx.get_transform().expect("unexpected exception");
-        self.ctx.clear_rect(
-            -mat.e(),
-            -mat.f(),
-            canvas.width().into(),
-            canvas.height().into(),
-        );
+        self.ctx.save();
+        self.ctx
+            .set_transform(1., 0., 0., 1., 0., 0.)
+            .expect("unexpected exception");
+        self.ctx
+            .clear_rect(0., 0., canvas.width().into(), canvas.height().into());
+        self.ctx.restore();
     }
 }
 
+/// Decodes the value from the bytes made by [`encode_bytes_into`].
+pub fn decode_bytes<T>(bytes: impl io::Read) -> bincode::Result<T>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let deflate = flate2::read::DeflateDecoder::new(bytes);
+    bincode::deserialize_from(deflate)
+}
+
+/// Encodes the value into compact bytes, writing them to `writer`.
+pub fn encode_bytes_into<T>(val: &T, writer: impl io::Write) -> bincode::Result<()>
+where
+    T: Serialize,
+{
+    let mut deflate = flate2::write::DeflateEncoder::new(writer, flate2::Compression::fast());
+    bincode::serialize_into(&mut deflate, val)?;
+    deflate.finish()?;
+    Ok(())
+}
+
+/// Decodes the value from the text made by [`encode_into`].
+pub fn decode<T>(s: &str) -> bincode::Result<T>
+where
+    T: for<'de> Deserialize<'de>,
+{
+    let mut bytes = s.as_bytes();
+    decode_bytes(base64::read::DecoderReader::new(
+        &mut bytes,
+        base64::STANDARD_NO_PAD,
+    ))
+}
+
+/// Encodes the value into a compact text, appending it to `s`.
+pub fn encode_into<T>(val: &T, s: &mut String) -> bincode::Result<()>
+where
+    T: Serialize,
+{
+    let mut base64 = base64::write::EncoderStringWriter::from(s, base64::STANDARD_NO_PAD);
+    encode_bytes_into(val, &mut base64)?;
+    base64.into_inner();
+    Ok(())
+}
+
+/// The `localStorage` of the browser.
 #[derive(Clone, Debug)]
-pub struct Storage(web_sys::Storage);
+pub struct LocalStorage(web_sys::Storage);
 
-impl Storage {
-    pub fn local() -> Option<Self> {
+impl LocalStorage {
+    pub fn new() -> Option<Self> {
         let storage = WINDOW
             .with(|w| w.local_storage())
             .expect("unexpected exception")?;
         Some(Self(storage))
     }
+}
 
-    pub fn get<T>(&self, key: &str) -> Option<Result<T>>
-    where
-        T: for<'de> Deserialize<'de>,
-    {
+impl Storage for LocalStorage {
+    fn get_item(&self, key: &str) -> Option<String> {
+        self.0.get_item(key).expect("unexpected exception")
+    }
+
+    fn set_item(&self, key: &str, value: &str) -> Result<()> {
         self.0
-            .get_item(key)
-            .expect("unexpected exception")
-            .map(|s| Ok(Self::load_from(&s)?))
+            .set_item(key, value)
+            .map_err(|e| anyhow!("exception (the storage is full?): {:?}", e))
     }
 
-    pub fn set<T>(&self, key: &str, val: &T) -> Result<()>
-    where
-        T: Serialize,
-    {
-        thread_local! {
-            static BUF: RefCell<String> = RefCell::new(String::new());
+    fn remove(&self, key: &str) {
+        self.0.remove_item(key).expect("unexpected exception");
+    }
+
+    fn keys(&self) -> Vec<String> {
+        let len = self.0.length().expect("unexpected exception");
+        (0..len)
+            .filter_map(|i| self.0.key(i).expect("unexpected exception"))
+            .collect()
+    }
+}
+
+/// Returns the future which resolves to the result of the request once it succeeds.
+///
+/// This must be called before the request is done, i.e. without awaiting anything after
+/// the request is made.
+fn idb_request_result(request: &web_sys::IdbRequest) -> impl Future<Output = Result<JsValue>> {
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        request.set_onsuccess(Some(&resolve));
+        request.set_onerror(Some(&reject));
+    });
+    let request = request.clone();
+    async move {
+        JsFuture::from(promise)
+            .await
+            .map_err(|_| anyhow!("request failed: {:?}", request.error()))?;
+        Ok(request.result().expect("unexpected exception"))
+    }
+}
+
+/// Returns the future which resolves once the transaction is committed,
+/// with the same restriction as [`idb_request_result`].
+fn idb_transaction_complete(
+    transaction: &web_sys::IdbTransaction,
+) -> impl Future<Output = Result<()>> {
+    let promise = js_sys::Promise::new(&mut |resolve, reject| {
+        transaction.set_oncomplete(Some(&resolve));
+        transaction.set_onerror(Some(&reject));
+        transaction.set_onabort(Some(&reject));
+    });
+    let transaction = transaction.clone();
+    async move {
+        JsFuture::from(promise)
+            .await
+            .map_err(|_| anyhow!("transaction failed: {:?}", transaction.error()))?;
+        Ok(())
+    }
+}
+
+/// A storage on IndexedDB, which has far more room than `localStorage`.
+///
+/// The whole content is loaded into memory on opening, so that it is read synchronously.
+/// Writes are applied to the memory at once and to the database in the background,
+/// where failures are reported to the callback given on opening.
+pub struct IndexedDbStorage {
+    db: web_sys::IdbDatabase,
+    cache: MemoryStorage,
+    /// Reports a failure of writing to the database with its cause.
+    report_error: Rc<dyn Fn(String)>,
+    /// The handler of the events of failed transactions, which calls `report_error`.
+    on_error: js_sys::Function,
+    /// Whether a write has failed since the database was last written in full,
+    /// which leaves it out of sync with `cache`.
+    is_out_of_sync: Rc<Cell<bool>>,
+}
+
+impl fmt::Debug for IndexedDbStorage {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("IndexedDbStorage")
+            .field("db", &self.db)
+            .field("cache", &self.cache)
+            .finish_non_exhaustive()
+    }
+}
+
+impl IndexedDbStorage {
+    const DB_NAME: &'static str = "papirs";
+    const DB_VERSION: u32 = 1;
+    const STORE_NAME: &'static str = "storage";
+
+    /// Opens the database and loads its content, moving the data of papirs left in
+    /// `localStorage` into it.
+    ///
+    /// Since values are written asynchronously, failures of writing are reported to `on_error`
+    /// later, and then the database is written in full on the next write.
+    pub async fn open(on_error: impl Fn(String) + 'static) -> Result<Self> {
+        let js_err = |e: JsValue| anyhow!("exception: {:?}", e);
+        let factory = WINDOW
+            .with(|w| w.indexed_db())
+            .map_err(js_err)?
+            .ok_or_else(|| anyhow!("IndexedDB not supported"))?;
+        let request = factory
+            .open_with_u32(Self::DB_NAME, Self::DB_VERSION)
+            .map_err(js_err)?;
+        listen_event(&request, "upgradeneeded", {
+            let request = request.clone();
+            move |_: web_sys::Event| {
+                let db = request.result().expect("unexpected exception");
+                let db = db.unchecked_into::<web_sys::IdbDatabase>();
+                if let Err(err) = db.create_object_store(Self::STORE_NAME) {
+                    log::error!("failed to create the object store: {:?}", err);
+                }
+            }
+        });
+        let db = idb_request_result(&request)
+            .await?
+            .unchecked_into::<web_sys::IdbDatabase>();
+
+        let store = db
+            .transaction_with_str(Self::STORE_NAME)
+            .and_then(|t| t.object_store(Self::STORE_NAME))
+            .map_err(js_err)?;
+        let keys = idb_request_result(&store.get_all_keys().map_err(js_err)?);
+        let values = idb_request_result(&store.get_all().map_err(js_err)?);
+        let (keys, values) = (keys.await?, values.await?);
+        let (keys, values) = (js_sys::Array::from(&keys), js_sys::Array::from(&values));
+        let cache = MemoryStorage::default();
+        for (key, value) in keys.iter().zip(values.iter()) {
+            if let (Some(key), Some(value)) = (key.as_string(), value.as_string()) {
+                cache.set_item(&key, &value)?;
+            }
         }
-        BUF.with(|s| {
-            let s = &mut *s.borrow_mut();
-            s.clear();
-            Self::save_to(val, s)?;
-            self.0
-                .set_item(key, s)
-                .map_err(|e| anyhow!("exception (the storage is full?): {:?}", e))
-        })
+
+        let is_out_of_sync = Rc::new(Cell::new(false));
+        let report_error = Rc::new({
+            let is_out_of_sync = Rc::clone(&is_out_of_sync);
+            move |cause: String| {
+                log::error!("failed to write to IndexedDB: {}", cause);
+                is_out_of_sync.set(true);
+                on_error(cause);
+            }
+        }) as Rc<dyn Fn(_)>;
+        let on_error = Closure::wrap(Box::new({
+            let report_error = Rc::clone(&report_error);
+            move |event: web_sys::Event| {
+                let target = event.target();
+                // the target is the request on an error, or the transaction on an abort
+                let error = (target.as_ref())
+                    .and_then(|t| t.dyn_ref::<web_sys::IdbRequest>())
+                    .and_then(|r| r.error().ok().flatten())
+                    .or_else(|| {
+                        (target.as_ref())
+                            .and_then(|t| t.dyn_ref::<web_sys::IdbTransaction>())
+                            .and_then(|t| t.error())
+                    });
+                report_error(error.map_or_else(|| "unknown error".to_owned(), |e| e.message()));
+            }
+        }) as Box<dyn FnMut(_)>)
+        .into_js_value()
+        .unchecked_into();
+        let this = Self {
+            db,
+            cache,
+            report_error,
+            on_error,
+            is_out_of_sync,
+        };
+        this.move_from_local_storage().await?;
+        Ok(this)
     }
 
-    pub fn remove(&self, key: &str) {
-        self.0.remove_item(key).expect("unexpected exception");
+    /// Moves the data of papirs in `localStorage` into the database, overwriting the values
+    /// under the same keys.
+    ///
+    /// The data is left there by the first run since `localStorage` was used instead,
+    /// or by a run when the database was unavailable, which is newer than that in the database.
+    async fn move_from_local_storage(&self) -> Result<()> {
+        let local = match LocalStorage::new() {
+            Some(local) => local,
+            None => return Ok(()),
+        };
+        let keys = (local.keys().into_iter())
+            .filter(|key| key.starts_with("papirs:"))
+            .collect::<Vec<_>>();
+        if keys.is_empty() {
+            return Ok(());
+        }
+        let js_err = |e: JsValue| anyhow!("exception: {:?}", e);
+        let transaction = self
+            .db
+            .transaction_with_str_and_mode(Self::STORE_NAME, web_sys::IdbTransactionMode::Readwrite)
+            .map_err(js_err)?;
+        let store = transaction.object_store(Self::STORE_NAME).map_err(js_err)?;
+        for key in &keys {
+            if let Some(value) = local.get_item(key) {
+                store
+                    .put_with_key(&value.as_str().into(), &key.as_str().into())
+                    .map_err(js_err)?;
+                self.cache.set_item(key, &value)?;
+            }
+        }
+        idb_transaction_complete(&transaction).await?;
+        // removed only after written, so that nothing is lost if failed
+        for key in &keys {
+            local.remove(key);
+        }
+        log::info!(
+            "moved {} items from localStorage into IndexedDB",
+            keys.len()
+        );
+        Ok(())
     }
 
-    fn load_from<T>(s: &str) -> bincode::Result<T>
-    where
-        T: for<'de> Deserialize<'de>,
-    {
-        let mut bytes = s.as_bytes();
-        let base64 = base64::read::DecoderReader::new(&mut bytes, base64::STANDARD_NO_PAD);
-        let deflate = flate2::read::DeflateDecoder::new(base64);
-        bincode::deserialize_from(deflate)
+    /// Makes a request to the object store in a new transaction, which is performed
+    /// after those made before.
+    ///
+    /// If a write has failed, the whole content of `cache` (already updated by the request)
+    /// is written instead, so that the database does not keep a part of the changes.
+    fn write(
+        &self,
+        request: impl FnOnce(&web_sys::IdbObjectStore) -> Result<web_sys::IdbRequest, JsValue>,
+    ) {
+        let result = (self.db)
+            .transaction_with_str_and_mode(Self::STORE_NAME, web_sys::IdbTransactionMode::Readwrite)
+            .and_then(|transaction| {
+                transaction.set_onerror(Some(&self.on_error));
+                transaction.set_onabort(Some(&self.on_error));
+                let store = transaction.object_store(Self::STORE_NAME)?;
+                if !self.is_out_of_sync.replace(false) {
+                    return request(&store).map(drop);
+                }
+                log::info!("writing the whole storage to IndexedDB again");
+                store.clear()?;
+                for key in self.cache.keys() {
+                    if let Some(value) = self.cache.get_item(&key) {
+                        store.put_with_key(&value.into(), &key.into())?;
+                    }
+                }
+                Ok(())
+            });
+        if let Err(err) = result {
+            (self.report_error)(format!("{:?}", err));
+        }
     }
+}
 
-    fn save_to<T>(val: &T, s: &mut String) -> bincode::Result<()>
-    where
-        T: Serialize,
-    {
-        let mut base64 = base64::write::EncoderStringWriter::from(s, base64::STANDARD_NO_PAD);
-        let deflate = flate2::write::DeflateEncoder::new(&mut base64, flate2::Compression::fast());
-        bincode::serialize_into(deflate, val)?;
-        base64.into_inner();
+impl Storage for IndexedDbStorage {
+    fn get_item(&self, key: &str) -> Option<String> {
+        self.cache.get_item(key)
+    }
+
+    fn set_item(&self, key: &str, value: &str) -> Result<()> {
+        self.cache.set_item(key, value)?;
+        self.write(|store| store.put_with_key(&value.into(), &key.into()));
         Ok(())
     }
+
+    fn remove(&self, key: &str) {
+        self.cache.remove(key);
+        self.write(|store| store.delete(&key.into()));
+    }
+
+    fn keys(&self) -> Vec<String> {
+        self.cache.keys()
+    }
 }